@@ -0,0 +1,1113 @@
+//! Contract ABI support, used to encode native Lua values
+//! into calldata following the Cairo types of a function,
+//! and to decode returned data into Lua values.
+use mlua::{Lua, Table, Value};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::FromPrimitive;
+use starknet::{
+    core::{
+        types::{
+            contract::{
+                AbiEntry, AbiEnum, AbiFunction, AbiNamedMember, AbiOutput, AbiStruct, SierraClass,
+            },
+            BlockId, ContractClass, FieldElement,
+        },
+        utils::{cairo_short_string_to_felt, parse_cairo_short_string},
+    },
+    providers::{AnyProvider, Provider},
+};
+use std::collections::HashMap;
+use std::slice::Iter;
+
+use crate::artifacts::{self, LocatedArtifacts};
+use crate::error::{Error, KiptResult};
+
+/// Number of bytes packed into a single felt of a `ByteArray`.
+const BYTES31_LEN: usize = 31;
+
+/// The ABI of a contract, indexed to quickly resolve
+/// functions and user defined types.
+#[derive(Debug, Clone, Default)]
+pub struct ContractAbi {
+    functions: HashMap<String, AbiFunction>,
+    constructor: Vec<AbiNamedMember>,
+    structs: HashMap<String, AbiStruct>,
+    enums: HashMap<String, AbiEnum>,
+}
+
+impl ContractAbi {
+    /// Builds the ABI from the entries found in a Sierra class.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - ABI entries of the contract.
+    pub fn from_entries(entries: Vec<AbiEntry>) -> Self {
+        let mut abi = Self::default();
+        abi.index_entries(entries);
+        abi
+    }
+
+    /// Loads the ABI embedded in the Sierra artifact of a contract.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract_name` - Name of the contract to locate the artifacts for.
+    /// * `artifacts_dir` - The directory where to search for the files.
+    /// * `is_recursive` - If the search must be done recursively.
+    pub fn from_artifacts(
+        contract_name: &str,
+        artifacts_dir: &str,
+        is_recursive: bool,
+    ) -> KiptResult<Self> {
+        let sierra_path =
+            match artifacts::locate_artifacts(contract_name, artifacts_dir, is_recursive)? {
+                LocatedArtifacts::Sierra { sierra_path, .. } => sierra_path,
+                LocatedArtifacts::Legacy { .. } => {
+                    return Err(Error::Abi(format!(
+                        "ABI of the Cairo 0 contract {} is not supported",
+                        contract_name
+                    )))
+                }
+            };
+
+        let sierra_class =
+            serde_json::from_reader::<_, SierraClass>(std::fs::File::open(sierra_path)?)?;
+
+        Ok(Self::from_entries(sierra_class.abi))
+    }
+
+    /// Fetches the ABI of the class of a deployed contract.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - The provider to fetch the class from.
+    /// * `contract_address` - The deployed contract address.
+    /// * `block_id` - The block id against which the class is fetched.
+    pub async fn from_contract(
+        provider: &AnyProvider,
+        contract_address: FieldElement,
+        block_id: BlockId,
+    ) -> KiptResult<Self> {
+        match provider.get_class_at(block_id, contract_address).await? {
+            ContractClass::Sierra(class) => Ok(Self::from_entries(serde_json::from_str::<
+                Vec<AbiEntry>,
+            >(&class.abi)?)),
+            ContractClass::Legacy(_) => Err(Error::Abi(format!(
+                "Contract 0x{:064x} is a legacy contract, its ABI is not supported",
+                contract_address
+            ))),
+        }
+    }
+
+    /// Returns the inputs of the given function.
+    ///
+    /// # Arguments
+    ///
+    /// * `function_name` - Name of the function (without the interface path).
+    pub fn function_inputs(&self, function_name: &str) -> KiptResult<&[AbiNamedMember]> {
+        self.functions
+            .get(function_name)
+            .map(|f| f.inputs.as_slice())
+            .ok_or(Error::Abi(format!(
+                "Function `{}` not found in the contract ABI",
+                function_name
+            )))
+    }
+
+    /// Returns the outputs of the given function.
+    ///
+    /// # Arguments
+    ///
+    /// * `function_name` - Name of the function (without the interface path).
+    pub fn function_outputs(&self, function_name: &str) -> KiptResult<&[AbiOutput]> {
+        self.functions
+            .get(function_name)
+            .map(|f| f.outputs.as_slice())
+            .ok_or(Error::Abi(format!(
+                "Function `{}` not found in the contract ABI",
+                function_name
+            )))
+    }
+
+    /// Returns the inputs of the constructor, empty if the
+    /// contract has no constructor.
+    pub fn constructor_inputs(&self) -> &[AbiNamedMember] {
+        &self.constructor
+    }
+
+    /// Encodes the arguments given as a Lua table for the given inputs.
+    /// The table can either be array-like, following the inputs order,
+    /// or keyed by the inputs names, in which case any other key is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Inputs of the function to encode the arguments for.
+    /// * `args` - The Lua table containing the arguments.
+    pub fn encode_inputs(
+        &self,
+        inputs: &[AbiNamedMember],
+        args: &Table,
+    ) -> KiptResult<Vec<FieldElement>> {
+        let keys = args
+            .clone()
+            .pairs::<Value, Value>()
+            .map(|p| p.map(|(k, _)| k))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let is_named = keys.iter().any(|k| matches!(k, Value::String(_)));
+
+        if is_named {
+            for key in &keys {
+                let is_input = match key {
+                    Value::String(k) => inputs.iter().any(|i| k.as_bytes() == i.name.as_bytes()),
+                    _ => false,
+                };
+
+                if !is_input {
+                    let names = inputs
+                        .iter()
+                        .map(|i| i.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    return Err(Error::Abi(format!(
+                        "Unknown argument `{}`, expected the arguments: {}",
+                        lua_key_to_string(key),
+                        names
+                    )));
+                }
+            }
+        }
+
+        if !is_named && args.raw_len() != inputs.len() {
+            return Err(Error::Abi(format!(
+                "Expected {} arguments, got {}",
+                inputs.len(),
+                args.raw_len()
+            )));
+        }
+
+        let mut out = vec![];
+
+        for (i, input) in inputs.iter().enumerate() {
+            let value: Value = if is_named {
+                args.get(input.name.as_str())?
+            } else {
+                args.get(i + 1)?
+            };
+
+            self.encode(&input.r#type, &value, &mut out)
+                .map_err(|e| with_context(&format!("argument `{}`", input.name), e))?;
+        }
+
+        Ok(out)
+    }
+
+    /// Encodes a Lua value for the given Cairo type.
+    ///
+    /// # Arguments
+    ///
+    /// * `ty` - The Cairo type, as written in the ABI.
+    /// * `value` - The Lua value to encode.
+    /// * `out` - The felts buffer where the encoded value is appended.
+    pub fn encode(&self, ty: &str, value: &Value, out: &mut Vec<FieldElement>) -> KiptResult<()> {
+        match ty {
+            "()" => Ok(()),
+            "core::felt252" => {
+                out.push(felt_from_value(value)?);
+                Ok(())
+            }
+            "core::bool" => match value {
+                Value::Boolean(b) => {
+                    out.push(if *b {
+                        FieldElement::ONE
+                    } else {
+                        FieldElement::ZERO
+                    });
+                    Ok(())
+                }
+                _ => Err(mismatch(ty, value)),
+            },
+            "core::integer::u8" => encode_uint(ty, value, 8, out),
+            "core::integer::u16" => encode_uint(ty, value, 16, out),
+            "core::integer::u32" | "core::integer::usize" => encode_uint(ty, value, 32, out),
+            "core::integer::u64" => encode_uint(ty, value, 64, out),
+            "core::integer::u128" => encode_uint(ty, value, 128, out),
+            "core::integer::i8" => encode_int(ty, value, 8, out),
+            "core::integer::i16" => encode_int(ty, value, 16, out),
+            "core::integer::i32" => encode_int(ty, value, 32, out),
+            "core::integer::i64" => encode_int(ty, value, 64, out),
+            "core::integer::i128" => encode_int(ty, value, 128, out),
+            "core::integer::u256" => self.encode_u256(ty, value, out),
+            "core::starknet::eth_address::EthAddress" => encode_uint(ty, value, 160, out),
+            "core::bytes_31::bytes31" => encode_uint(ty, value, 248, out),
+            "core::starknet::contract_address::ContractAddress"
+            | "core::starknet::class_hash::ClassHash" => {
+                out.push(hex_or_dec_felt(ty, value)?);
+                Ok(())
+            }
+            "core::byte_array::ByteArray" => match value {
+                Value::String(s) => {
+                    out.extend(byte_array_to_felts(s.as_bytes())?);
+                    Ok(())
+                }
+                _ => Err(mismatch(ty, value)),
+            },
+            _ => {
+                if let Some(inner) = generic_arg(ty, "core::array::Array::<")
+                    .or_else(|| generic_arg(ty, "core::array::Span::<"))
+                {
+                    self.encode_array(ty, inner, value, out)
+                } else if ty.starts_with('(') {
+                    self.encode_tuple(ty, value, out)
+                } else if let Some(s) = self.structs.get(ty) {
+                    self.encode_struct(s, value, out)
+                } else if let Some(e) = self.enums.get(ty) {
+                    self.encode_enum(e, value, out)
+                } else {
+                    Err(Error::Abi(format!("Unsupported type `{}`", ty)))
+                }
+            }
+        }
+    }
+
+    /// Decodes the data returned by a function into a Lua array,
+    /// with one value per output.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - Lua VM instance.
+    /// * `outputs` - Outputs of the function.
+    /// * `data` - The felts returned by the function.
+    /// * `short_strings` - If `felt252` must be decoded as Cairo short strings.
+    pub fn decode_outputs<'lua>(
+        &self,
+        lua: &'lua Lua,
+        outputs: &[AbiOutput],
+        data: &[FieldElement],
+        short_strings: bool,
+    ) -> KiptResult<Table<'lua>> {
+        let t = lua.create_table()?;
+        let mut it = data.iter();
+
+        for (idx, output) in (1..).zip(outputs) {
+            t.set(
+                idx,
+                self.decode(lua, &output.r#type, &mut it, short_strings)?,
+            )?;
+        }
+
+        if it.next().is_some() {
+            return Err(Error::Abi(format!(
+                "{} felts returned, more than expected by the ABI",
+                data.len()
+            )));
+        }
+
+        Ok(t)
+    }
+
+    /// Decodes felts into a Lua value for the given Cairo type.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - Lua VM instance.
+    /// * `ty` - The Cairo type, as written in the ABI.
+    /// * `data` - The felts iterator to consume.
+    /// * `short_strings` - If `felt252` must be decoded as Cairo short strings.
+    pub fn decode<'lua>(
+        &self,
+        lua: &'lua Lua,
+        ty: &str,
+        data: &mut Iter<FieldElement>,
+        short_strings: bool,
+    ) -> KiptResult<Value<'lua>> {
+        match ty {
+            "()" => Ok(Value::Nil),
+            "core::felt252" => {
+                let felt = next_felt(ty, data)?;
+                match parse_cairo_short_string(&felt) {
+                    Ok(s) if short_strings => Ok(Value::String(lua.create_string(&s)?)),
+                    _ => Ok(Value::String(
+                        lua.create_string(format!("0x{:064x}", felt))?,
+                    )),
+                }
+            }
+            "core::bool" => Ok(Value::Boolean(next_felt(ty, data)? != FieldElement::ZERO)),
+            "core::integer::u8"
+            | "core::integer::u16"
+            | "core::integer::u32"
+            | "core::integer::usize"
+            | "core::integer::u64"
+            | "core::integer::u128" => {
+                let n = BigInt::from(biguint_from_felt(&next_felt(ty, data)?));
+                bigint_to_lua(lua, n)
+            }
+            "core::integer::i8"
+            | "core::integer::i16"
+            | "core::integer::i32"
+            | "core::integer::i64"
+            | "core::integer::i128" => {
+                let felt = next_felt(ty, data)?;
+                let n = biguint_from_felt(&felt);
+
+                // Negative values are represented as `P - |n|`, which never fit in 128 bits.
+                if n.bits() > 128 {
+                    bigint_to_lua(lua, -BigInt::from(biguint_from_felt(&-felt)))
+                } else {
+                    bigint_to_lua(lua, BigInt::from(n))
+                }
+            }
+            "core::integer::u256" => {
+                let low = biguint_from_felt(&next_felt(ty, data)?);
+                let high = biguint_from_felt(&next_felt(ty, data)?);
+                let n: BigUint = (high << 128_u32) + low;
+                Ok(Value::String(lua.create_string(n.to_string())?))
+            }
+            "core::starknet::contract_address::ContractAddress"
+            | "core::starknet::class_hash::ClassHash"
+            | "core::starknet::eth_address::EthAddress"
+            | "core::bytes_31::bytes31" => Ok(Value::String(
+                lua.create_string(format!("0x{:064x}", next_felt(ty, data)?))?,
+            )),
+            "core::byte_array::ByteArray" => {
+                let bytes = byte_array_from_felts(data)?;
+                Ok(Value::String(lua.create_string(&bytes)?))
+            }
+            _ => {
+                if let Some(inner) = generic_arg(ty, "core::array::Array::<")
+                    .or_else(|| generic_arg(ty, "core::array::Span::<"))
+                {
+                    let len = next_usize(ty, data)?;
+                    let t = lua.create_table()?;
+                    for idx in 1..=len {
+                        t.set(idx, self.decode(lua, inner, data, short_strings)?)?;
+                    }
+                    Ok(Value::Table(t))
+                } else if ty.starts_with('(') {
+                    let t = lua.create_table()?;
+                    for (idx, inner) in (1..).zip(tuple_types(ty)) {
+                        t.set(idx, self.decode(lua, &inner, data, short_strings)?)?;
+                    }
+                    Ok(Value::Table(t))
+                } else if let Some(s) = self.structs.get(ty) {
+                    let t = lua.create_table()?;
+                    for m in &s.members {
+                        t.set(
+                            m.name.as_str(),
+                            self.decode(lua, &m.r#type, data, short_strings)?,
+                        )?;
+                    }
+                    Ok(Value::Table(t))
+                } else if let Some(e) = self.enums.get(ty) {
+                    let idx = next_usize(ty, data)?;
+                    let variant = e.variants.get(idx).ok_or(Error::Abi(format!(
+                        "Variant index {} out of range for enum {}",
+                        idx, e.name
+                    )))?;
+
+                    let value = self.decode(lua, &variant.r#type, data, short_strings)?;
+
+                    if e.name.starts_with("core::option::Option::<") {
+                        // `None` is nil, `Some` is directly the inner value.
+                        Ok(value)
+                    } else if variant.r#type == "()" {
+                        Ok(Value::String(lua.create_string(&variant.name)?))
+                    } else {
+                        let t = lua.create_table()?;
+                        t.set(variant.name.as_str(), value)?;
+                        Ok(Value::Table(t))
+                    }
+                } else {
+                    Err(Error::Abi(format!("Unsupported type `{}`", ty)))
+                }
+            }
+        }
+    }
+
+    /// Indexes ABI entries, flattening the interfaces.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - ABI entries to be indexed.
+    fn index_entries(&mut self, entries: Vec<AbiEntry>) {
+        for entry in entries {
+            match entry {
+                AbiEntry::Function(f) | AbiEntry::L1Handler(f) => {
+                    self.functions.insert(f.name.clone(), f);
+                }
+                AbiEntry::Constructor(c) => self.constructor = c.inputs,
+                AbiEntry::Struct(s) => {
+                    self.structs.insert(s.name.clone(), s);
+                }
+                AbiEntry::Enum(e) => {
+                    self.enums.insert(e.name.clone(), e);
+                }
+                AbiEntry::Interface(i) => self.index_entries(i.items),
+                AbiEntry::Event(_) | AbiEntry::Impl(_) => {}
+            }
+        }
+    }
+
+    /// Encodes a `u256`, from a number, a string or a table with `low` and `high` keys.
+    fn encode_u256(&self, ty: &str, value: &Value, out: &mut Vec<FieldElement>) -> KiptResult<()> {
+        if let Value::Table(t) = value {
+            encode_uint(ty, &t.get::<_, Value>("low")?, 128, out)?;
+            return encode_uint(ty, &t.get::<_, Value>("high")?, 128, out);
+        }
+
+        let n = uint_in_range(ty, value, 256)?;
+        let mask = (BigUint::from(1_u8) << 128) - 1_u8;
+
+        out.push(felt_from_biguint(&(&n & &mask))?);
+        out.push(felt_from_biguint(&(n >> 128))?);
+
+        Ok(())
+    }
+
+    /// Encodes an array-like Lua table, prefixed by its length.
+    fn encode_array(
+        &self,
+        ty: &str,
+        inner: &str,
+        value: &Value,
+        out: &mut Vec<FieldElement>,
+    ) -> KiptResult<()> {
+        let t = match value {
+            Value::Table(t) => t,
+            _ => return Err(mismatch(ty, value)),
+        };
+
+        out.push(FieldElement::from(t.raw_len()));
+
+        for i in 1..=t.raw_len() {
+            self.encode(inner, &t.get::<_, Value>(i)?, out)
+                .map_err(|e| with_context(&format!("[{}]", i), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes an array-like Lua table as a tuple.
+    fn encode_tuple(&self, ty: &str, value: &Value, out: &mut Vec<FieldElement>) -> KiptResult<()> {
+        let types = tuple_types(ty);
+
+        let t = match value {
+            Value::Table(t) if t.raw_len() == types.len() => t,
+            _ => return Err(mismatch(ty, value)),
+        };
+
+        for (i, inner) in types.iter().enumerate() {
+            self.encode(inner, &t.get::<_, Value>(i + 1)?, out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes a Lua table as a struct, using the members names as keys.
+    fn encode_struct(
+        &self,
+        s: &AbiStruct,
+        value: &Value,
+        out: &mut Vec<FieldElement>,
+    ) -> KiptResult<()> {
+        let t = match value {
+            Value::Table(t) => t,
+            _ => return Err(mismatch(&s.name, value)),
+        };
+
+        for m in &s.members {
+            self.encode(&m.r#type, &t.get::<_, Value>(m.name.as_str())?, out)
+                .map_err(|e| with_context(&format!("{}.{}", s.name, m.name), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes an enum. A unit variant can be given by its name only,
+    /// other variants are expected as a table with one key (the variant name)
+    /// associated to the variant data. For `Option`, `nil` is accepted as `None`
+    /// and any other value as `Some`.
+    fn encode_enum(
+        &self,
+        e: &AbiEnum,
+        value: &Value,
+        out: &mut Vec<FieldElement>,
+    ) -> KiptResult<()> {
+        let explicit = explicit_variant(value)?;
+        let is_option = e.name.starts_with("core::option::Option::<");
+
+        let (variant, data): (String, Value) = match (explicit, value) {
+            (Some((v, d)), _) if !is_option || v == "Some" || v == "None" => (v, d),
+            (_, Value::Nil) if is_option => ("None".to_string(), Value::Nil),
+            (_, _) if is_option => ("Some".to_string(), value.clone()),
+            _ => return Err(mismatch(&e.name, value)),
+        };
+
+        let (idx, member) = e
+            .variants
+            .iter()
+            .enumerate()
+            .find(|(_, v)| v.name == variant)
+            .ok_or(Error::Abi(format!(
+                "Variant `{}` not found in enum {}",
+                variant, e.name
+            )))?;
+
+        out.push(FieldElement::from(idx));
+
+        self.encode(&member.r#type, &data, out)
+            .map_err(|err| with_context(&format!("{}::{}", e.name, variant), err))
+    }
+}
+
+/// Extracts the variant name and data of an enum given as a variant
+/// name, or as a table with the variant name as only key.
+///
+/// # Arguments
+///
+/// * `value` - The Lua value representing the enum.
+fn explicit_variant<'lua>(value: &Value<'lua>) -> KiptResult<Option<(String, Value<'lua>)>> {
+    match value {
+        Value::String(s) => Ok(Some((s.to_str()?.to_string(), Value::Nil))),
+        Value::Table(t) => {
+            let mut pairs = t
+                .clone()
+                .pairs::<Value, Value>()
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match (pairs.pop(), pairs.is_empty()) {
+                (Some((Value::String(k), v)), true) => Ok(Some((k.to_str()?.to_string(), v))),
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns the generic argument of the type if the type starts with the prefix.
+///
+/// # Arguments
+///
+/// * `ty` - The full type (like `core::array::Array::<core::felt252>`).
+/// * `prefix` - The prefix including the opening `<`.
+fn generic_arg<'a>(ty: &'a str, prefix: &str) -> Option<&'a str> {
+    ty.strip_prefix(prefix).and_then(|t| t.strip_suffix('>'))
+}
+
+/// Splits a tuple type into the types of its elements.
+///
+/// # Arguments
+///
+/// * `ty` - The tuple type (like `(core::felt252, core::bool)`).
+fn tuple_types(ty: &str) -> Vec<String> {
+    let inner = ty
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .unwrap_or(ty);
+
+    let mut types = vec![];
+    let mut depth = 0;
+    let mut current = String::new();
+
+    for c in inner.chars() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() {
+        types.push(current.trim().to_string());
+    }
+
+    types
+}
+
+/// Prefixes an encoding error with the location of the faulty value.
+fn with_context(ctx: &str, e: Error) -> Error {
+    match e {
+        Error::Abi(m) => Error::Abi(format!("{}: {}", ctx, m)),
+        other => Error::Abi(format!("{}: {}", ctx, other)),
+    }
+}
+
+/// Returns a printable representation of a Lua table key.
+fn lua_key_to_string(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.to_string_lossy().to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        other => other.type_name().to_string(),
+    }
+}
+
+/// Builds the type mismatch error.
+fn mismatch(ty: &str, value: &Value) -> Error {
+    Error::Abi(format!(
+        "Can't encode a Lua {} as `{}`",
+        value.type_name(),
+        ty
+    ))
+}
+
+/// Parses a Lua value (integer or decimal/hexadecimal string) into a big integer.
+///
+/// # Arguments
+///
+/// * `value` - The Lua value to be parsed.
+fn bigint_from_value(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Integer(i) => Some(BigInt::from(*i)),
+        // Only the integral numbers are exact, the other ones are rejected.
+        Value::Number(n) if n.fract() == 0.0 && n.is_finite() => BigInt::from_f64(*n),
+        Value::String(s) => {
+            let s = s.to_str().ok()?.trim();
+            let (negative, s) = match s.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, s),
+            };
+
+            let n = if let Some(hex) = s.strip_prefix("0x") {
+                BigInt::parse_bytes(hex.as_bytes(), 16)?
+            } else {
+                BigInt::parse_bytes(s.as_bytes(), 10)?
+            };
+
+            Some(if negative { -n } else { n })
+        }
+        _ => None,
+    }
+}
+
+/// Converts a big unsigned integer into a felt, if it fits.
+fn felt_from_biguint(n: &BigUint) -> KiptResult<FieldElement> {
+    FieldElement::from_byte_slice_be(&n.to_bytes_be())
+        .map_err(|_| Error::Abi(format!("Value {} doesn't fit into a felt252", n)))
+}
+
+/// Converts a big integer into a felt, negative values being
+/// represented as `P - |n|`.
+fn felt_from_bigint(n: &BigInt) -> KiptResult<FieldElement> {
+    let felt = felt_from_biguint(n.magnitude())?;
+
+    if n.sign() == Sign::Minus {
+        Ok(-felt)
+    } else {
+        Ok(felt)
+    }
+}
+
+/// Converts a Lua value into a felt. Non numerical strings are
+/// encoded as Cairo short strings.
+fn felt_from_value(value: &Value) -> KiptResult<FieldElement> {
+    if let Some(n) = bigint_from_value(value) {
+        return felt_from_bigint(&n);
+    }
+
+    match value {
+        Value::String(s) => cairo_short_string_to_felt(s.to_str()?).map_err(|_| {
+            Error::Abi(format!(
+                "Invalid short string: {}",
+                s.to_str().unwrap_or("")
+            ))
+        }),
+        _ => Err(mismatch("core::felt252", value)),
+    }
+}
+
+/// Converts a Lua value into a felt, only accepting numerical values.
+fn hex_or_dec_felt(ty: &str, value: &Value) -> KiptResult<FieldElement> {
+    let n = bigint_from_value(value).ok_or(mismatch(ty, value))?;
+    felt_from_bigint(&n)
+}
+
+/// Parses an unsigned integer and checks that it fits in the given bits.
+fn uint_in_range(ty: &str, value: &Value, bits: u32) -> KiptResult<BigUint> {
+    let n = bigint_from_value(value).ok_or(mismatch(ty, value))?;
+
+    match n.to_biguint() {
+        Some(u) if u.bits() <= bits.into() => Ok(u),
+        _ => Err(Error::Abi(format!(
+            "Value {} is out of range for `{}`",
+            n, ty
+        ))),
+    }
+}
+
+/// Encodes an unsigned integer of the given bits.
+fn encode_uint(ty: &str, value: &Value, bits: u32, out: &mut Vec<FieldElement>) -> KiptResult<()> {
+    let n = uint_in_range(ty, value, bits)?;
+    out.push(felt_from_biguint(&n)?);
+    Ok(())
+}
+
+/// Encodes a signed integer of the given bits.
+fn encode_int(ty: &str, value: &Value, bits: u32, out: &mut Vec<FieldElement>) -> KiptResult<()> {
+    let n = bigint_from_value(value).ok_or(mismatch(ty, value))?;
+    let bound = BigInt::from(1_u8) << (bits - 1);
+
+    if n < -bound.clone() || n >= bound {
+        return Err(Error::Abi(format!(
+            "Value {} is out of range for `{}`",
+            n, ty
+        )));
+    }
+
+    out.push(felt_from_bigint(&n)?);
+    Ok(())
+}
+
+/// Serializes bytes as a Cairo `ByteArray`: the full 31 bytes words,
+/// followed by the pending word and its length.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to be serialized.
+fn byte_array_to_felts(bytes: &[u8]) -> KiptResult<Vec<FieldElement>> {
+    let chunks: Vec<&[u8]> = bytes.chunks(BYTES31_LEN).collect();
+
+    let (full, pending): (&[&[u8]], &[u8]) = match chunks.last() {
+        Some(last) if last.len() < BYTES31_LEN => (&chunks[..chunks.len() - 1], last),
+        _ => (&chunks[..], &[]),
+    };
+
+    let mut out = vec![FieldElement::from(full.len())];
+
+    for word in full {
+        out.push(FieldElement::from_byte_slice_be(word).map_err(|e| Error::Abi(e.to_string()))?);
+    }
+
+    out.push(FieldElement::from_byte_slice_be(pending).map_err(|e| Error::Abi(e.to_string()))?);
+    out.push(FieldElement::from(pending.len()));
+
+    Ok(out)
+}
+
+/// Returns the next felt to be decoded.
+fn next_felt(ty: &str, data: &mut Iter<FieldElement>) -> KiptResult<FieldElement> {
+    data.next().copied().ok_or(Error::Abi(format!(
+        "Not enough data returned to decode `{}`",
+        ty
+    )))
+}
+
+/// Returns the next felt to be decoded as a length or an index.
+fn next_usize(ty: &str, data: &mut Iter<FieldElement>) -> KiptResult<usize> {
+    let n = biguint_from_felt(&next_felt(ty, data)?);
+    usize::try_from(&n).map_err(|_| Error::Abi(format!("Invalid length {} for `{}`", n, ty)))
+}
+
+/// Converts a felt into a big unsigned integer.
+fn biguint_from_felt(felt: &FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&felt.to_bytes_be())
+}
+
+/// Converts a big integer into a Lua integer if it fits,
+/// else into a decimal string.
+fn bigint_to_lua<'lua>(lua: &'lua Lua, n: BigInt) -> KiptResult<Value<'lua>> {
+    match i64::try_from(&n) {
+        Ok(i) => Ok(Value::Integer(i)),
+        Err(_) => Ok(Value::String(lua.create_string(n.to_string())?)),
+    }
+}
+
+/// Deserializes a Cairo `ByteArray` into bytes.
+///
+/// # Arguments
+///
+/// * `data` - The felts iterator to consume.
+fn byte_array_from_felts(data: &mut Iter<FieldElement>) -> KiptResult<Vec<u8>> {
+    let ty = "core::byte_array::ByteArray";
+    let mut bytes = vec![];
+
+    for _ in 0..next_usize(ty, data)? {
+        bytes.extend_from_slice(&next_felt(ty, data)?.to_bytes_be()[32 - BYTES31_LEN..]);
+    }
+
+    let pending_word = next_felt(ty, data)?.to_bytes_be();
+    let pending_len = next_usize(ty, data)?;
+
+    if pending_len >= BYTES31_LEN {
+        return Err(Error::Abi(format!(
+            "Invalid ByteArray pending word length: {}",
+            pending_len
+        )));
+    }
+
+    bytes.extend_from_slice(&pending_word[32 - pending_len..]);
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABI: &str = r#"[
+        {
+            "type": "struct",
+            "name": "test::Point",
+            "members": [
+                { "name": "x", "type": "core::felt252" },
+                { "name": "y", "type": "core::integer::u32" }
+            ]
+        },
+        {
+            "type": "enum",
+            "name": "core::option::Option::<core::integer::u32>",
+            "variants": [
+                { "name": "Some", "type": "core::integer::u32" },
+                { "name": "None", "type": "()" }
+            ]
+        },
+        {
+            "type": "enum",
+            "name": "test::Move",
+            "variants": [
+                { "name": "Stay", "type": "()" },
+                { "name": "Jump", "type": "core::integer::u8" }
+            ]
+        },
+        {
+            "type": "function",
+            "name": "set",
+            "inputs": [
+                { "name": "key", "type": "core::felt252" },
+                { "name": "value", "type": "core::integer::u256" }
+            ],
+            "outputs": [{ "type": "core::integer::u256" }],
+            "state_mutability": "external"
+        }
+    ]"#;
+
+    fn abi() -> ContractAbi {
+        ContractAbi::from_entries(serde_json::from_str(ABI).unwrap())
+    }
+
+    fn eval<'lua>(lua: &'lua Lua, expr: &str) -> Value<'lua> {
+        lua.load(format!("return {}", expr)).eval().unwrap()
+    }
+
+    fn felts(values: &[&str]) -> Vec<FieldElement> {
+        values
+            .iter()
+            .map(|v| FieldElement::from_hex_be(v).unwrap())
+            .collect()
+    }
+
+    fn encode(lua: &Lua, ty: &str, expr: &str) -> KiptResult<Vec<FieldElement>> {
+        let mut out = vec![];
+        abi().encode(ty, &eval(lua, expr), &mut out)?;
+        Ok(out)
+    }
+
+    /// Decodes the felts and returns the `tostring` of the Lua value,
+    /// checking that all the felts were consumed.
+    fn decode(lua: &Lua, ty: &str, data: &[FieldElement]) -> String {
+        let mut it = data.iter();
+        let value = abi().decode(lua, ty, &mut it, false).unwrap();
+        assert!(it.next().is_none(), "trailing data after `{}`", ty);
+
+        let tostring: mlua::Function = lua.globals().get("tostring").unwrap();
+        tostring.call(value).unwrap()
+    }
+
+    /// Encodes the value, decodes it and encodes the decoded value again.
+    fn round_trip(lua: &Lua, ty: &str, expr: &str) -> Vec<FieldElement> {
+        let data = encode(lua, ty, expr).unwrap();
+
+        let mut it = data.iter();
+        let value = abi().decode(lua, ty, &mut it, false).unwrap();
+        assert!(it.next().is_none(), "trailing data after `{}`", ty);
+
+        let mut again = vec![];
+        abi().encode(ty, &value, &mut again).unwrap();
+        assert_eq!(data, again, "round trip of `{}` for `{}`", expr, ty);
+
+        data
+    }
+
+    #[test]
+    fn u256_is_split_into_low_and_high() {
+        let lua = Lua::new();
+        let ty = "core::integer::u256";
+
+        // 2^128 + 5
+        let data = round_trip(&lua, ty, "\"0x100000000000000000000000000000005\"");
+        assert_eq!(data, felts(&["0x5", "0x1"]));
+        assert_eq!(
+            decode(&lua, ty, &data),
+            "340282366920938463463374607431768211461"
+        );
+
+        assert_eq!(
+            encode(&lua, ty, "{ low = 1, high = 2 }").unwrap(),
+            felts(&["0x1", "0x2"])
+        );
+
+        assert!(encode(&lua, ty, "-1").is_err());
+    }
+
+    #[test]
+    fn signed_ints_are_encoded_as_field_negatives() {
+        let lua = Lua::new();
+
+        let data = round_trip(&lua, "core::integer::i8", "-1");
+        assert_eq!(data, vec![-FieldElement::ONE]);
+        assert_eq!(decode(&lua, "core::integer::i8", &data), "-1");
+
+        let data = round_trip(
+            &lua,
+            "core::integer::i128",
+            "\"-170141183460469231731687303715884105728\"",
+        );
+        assert_eq!(
+            data,
+            vec![-FieldElement::from_hex_be("0x80000000000000000000000000000000").unwrap()]
+        );
+
+        assert!(encode(&lua, "core::integer::i8", "-129").is_err());
+        assert!(encode(&lua, "core::integer::i8", "128").is_err());
+        assert_eq!(
+            round_trip(&lua, "core::integer::i8", "127"),
+            felts(&["0x7f"])
+        );
+    }
+
+    #[test]
+    fn byte_array_is_chunked_by_31_bytes() {
+        let lua = Lua::new();
+        let ty = "core::byte_array::ByteArray";
+
+        let data = round_trip(&lua, ty, "\"\"");
+        assert_eq!(data, felts(&["0x0", "0x0", "0x0"]));
+
+        let data = round_trip(&lua, ty, "\"abc\"");
+        assert_eq!(data, felts(&["0x0", "0x616263", "0x3"]));
+
+        // Exact multiples of 31 bytes have an empty pending word.
+        let data = round_trip(&lua, ty, "string.rep(\"a\", 31)");
+        assert_eq!(data.len(), 4);
+        assert_eq!(data[0], FieldElement::ONE);
+        assert_eq!(&data[2..], &felts(&["0x0", "0x0"]));
+
+        let data = round_trip(&lua, ty, "string.rep(\"a\", 62)");
+        assert_eq!(data.len(), 5);
+        assert_eq!(data[0], FieldElement::TWO);
+        assert_eq!(&data[3..], &felts(&["0x0", "0x0"]));
+
+        let data = round_trip(&lua, ty, "string.rep(\"a\", 32)");
+        assert_eq!(data.len(), 4);
+        assert_eq!(&data[2..], &felts(&["0x61", "0x1"]));
+        assert_eq!(decode(&lua, ty, &data), "a".repeat(32));
+    }
+
+    #[test]
+    fn option_and_enum_variants() {
+        let lua = Lua::new();
+        let option = "core::option::Option::<core::integer::u32>";
+
+        assert_eq!(round_trip(&lua, option, "nil"), felts(&["0x1"]));
+        assert_eq!(round_trip(&lua, option, "7"), felts(&["0x0", "0x7"]));
+        assert_eq!(
+            encode(&lua, option, "{ Some = 7 }").unwrap(),
+            felts(&["0x0", "0x7"])
+        );
+        assert_eq!(decode(&lua, option, &felts(&["0x1"])), "nil");
+        assert_eq!(decode(&lua, option, &felts(&["0x0", "0x7"])), "7");
+
+        let ty = "test::Move";
+        assert_eq!(round_trip(&lua, ty, "\"Stay\""), felts(&["0x0"]));
+        assert_eq!(round_trip(&lua, ty, "{ Jump = 3 }"), felts(&["0x1", "0x3"]));
+        assert_eq!(decode(&lua, ty, &felts(&["0x0"])), "Stay");
+
+        assert!(encode(&lua, ty, "\"Fly\"").is_err());
+        assert!(encode(&lua, ty, "{ Jump = 256 }").is_err());
+    }
+
+    #[test]
+    fn tuples_are_split_at_the_top_level() {
+        assert_eq!(
+            tuple_types("(core::felt252, (core::integer::u8, core::bool), core::array::Array::<(core::felt252, core::felt252)>)"),
+            vec![
+                "core::felt252",
+                "(core::integer::u8, core::bool)",
+                "core::array::Array::<(core::felt252, core::felt252)>",
+            ]
+        );
+
+        let lua = Lua::new();
+        let ty = "(core::felt252, (core::integer::u8, core::bool), test::Point)";
+        assert_eq!(
+            round_trip(&lua, ty, "{ 1, { 2, true }, { x = 3, y = 4 } }"),
+            felts(&["0x1", "0x2", "0x1", "0x3", "0x4"])
+        );
+
+        assert!(encode(&lua, ty, "{ 1, { 2, true } }").is_err());
+    }
+
+    #[test]
+    fn outputs_must_consume_all_the_data() {
+        let lua = Lua::new();
+        let abi = abi();
+        let outputs = abi.function_outputs("set").unwrap();
+
+        let t = abi
+            .decode_outputs(&lua, outputs, &felts(&["0x1", "0x0"]), false)
+            .unwrap();
+        assert_eq!(t.get::<_, String>(1).unwrap(), "1");
+
+        assert!(abi
+            .decode_outputs(&lua, outputs, &felts(&["0x1", "0x0", "0x2"]), false)
+            .is_err());
+        assert!(abi
+            .decode_outputs(&lua, outputs, &felts(&["0x1"]), false)
+            .is_err());
+    }
+
+    #[test]
+    fn numbers_are_converted_exactly() {
+        let lua = Lua::new();
+        let ty = "core::integer::u256";
+
+        // 2^130 doesn't fit in an i128.
+        assert_eq!(
+            encode(&lua, ty, "2.0 ^ 130").unwrap(),
+            felts(&["0x0", "0x4"])
+        );
+
+        assert!(encode(&lua, ty, "1.5").is_err());
+        assert!(encode(&lua, ty, "math.huge").is_err());
+        assert!(encode(&lua, ty, "0 / 0").is_err());
+    }
+
+    #[test]
+    fn named_arguments_are_checked() {
+        let lua = Lua::new();
+        let abi = abi();
+        let inputs = abi.function_inputs("set").unwrap();
+
+        let args = |expr: &str| match eval(&lua, expr) {
+            Value::Table(t) => abi.encode_inputs(inputs, &t),
+            _ => unreachable!(),
+        };
+
+        let expected = felts(&["0x1", "0x2", "0x0"]);
+        assert_eq!(args("{ 1, 2 }").unwrap(), expected);
+        assert_eq!(args("{ key = 1, value = 2 }").unwrap(), expected);
+        assert_eq!(args("{ value = 2, key = 1 }").unwrap(), expected);
+
+        assert!(args("{ key = 1, value = 2, extra = 3 }").is_err());
+        assert!(args("{ value = 2, key = 1, 3 }").is_err());
+        assert!(args("{ 1 }").is_err());
+        assert!(args("{ 1, 2, 3 }").is_err());
+    }
+}
@@ -1,14 +1,121 @@
+use lazy_static::lazy_static;
+use serde::Deserialize;
 use starknet::{
     accounts::{ExecutionEncoding, SingleOwnerAccount},
     core::types::FieldElement,
     providers::{
         jsonrpc::HttpTransport, AnyProvider, JsonRpcClient, Provider, SequencerGatewayProvider,
     },
-    signers::{LocalWallet, SigningKey},
+    signers::Signer,
 };
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 use url::Url;
 
 use crate::error::{Error, KiptResult};
+use crate::signer::KiptSigner;
+
+/// Account used to send the transactions, signed by the kipt signer.
+pub type KiptAccount = SingleOwnerAccount<AnyProvider, KiptSigner>;
+
+lazy_static! {
+    /// Accounts already set up, to reuse their connection and chain id
+    /// across transactions. As the key is built from the globals,
+    /// a change of the globals selects (or sets up) another account.
+    static ref ACCOUNTS: Mutex<HashMap<AccountKey, Arc<KiptAccount>>> =
+        Mutex::new(HashMap::new());
+
+    /// Chain ids already fetched, by RPC URL or network name.
+    static ref CHAIN_IDS: Mutex<HashMap<String, FieldElement>> = Mutex::new(HashMap::new());
+}
+
+/// Key of the accounts cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AccountKey {
+    url_network: String,
+    address: FieldElement,
+    public_key: FieldElement,
+    is_legacy: bool,
+    chain_id: Option<FieldElement>,
+}
+
+/// An account descriptor, as written by starkli (`starkli account fetch`
+/// or `starkli account oz init` for instance).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountDescriptor {
+    pub variant: AccountVariant,
+    pub deployment: AccountDeployment,
+}
+
+/// Class type of the account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccountVariant {
+    OpenZeppelin {
+        /// Starkli descriptors without this field are Cairo 0 accounts.
+        #[serde(default = "default_oz_legacy")]
+        legacy: bool,
+    },
+    Argent {
+        /// Only Cairo 0 Argent accounts are proxies with an implementation.
+        #[serde(default)]
+        implementation: Option<FieldElement>,
+    },
+    Braavos {},
+}
+
+/// Deployment status of the account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AccountDeployment {
+    Undeployed { class_hash: FieldElement },
+    Deployed { address: FieldElement },
+}
+
+fn default_oz_legacy() -> bool {
+    true
+}
+
+impl AccountDescriptor {
+    /// Loads an account descriptor from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the account file.
+    pub fn load(path: &Path) -> KiptResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::Other(format!(
+                "Account file can't be read {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Returns the address of the account, which must be deployed.
+    pub fn address(&self) -> KiptResult<FieldElement> {
+        match self.deployment {
+            AccountDeployment::Deployed { address } => Ok(address),
+            AccountDeployment::Undeployed { class_hash } => Err(Error::Other(format!(
+                "Account of class 0x{:064x} is not deployed yet",
+                class_hash
+            ))),
+        }
+    }
+
+    /// Returns true if the account expects the legacy (Cairo 0) execution encoding.
+    pub fn is_legacy(&self) -> bool {
+        match &self.variant {
+            AccountVariant::OpenZeppelin { legacy } => *legacy,
+            AccountVariant::Argent { implementation } => implementation.is_some(),
+            AccountVariant::Braavos {} => true,
+        }
+    }
+}
 
 /// Setups a provider from the provided information.
 ///
@@ -27,41 +134,92 @@ pub async fn setup_provider(url_network: &str) -> KiptResult<AnyProvider> {
 }
 
 /// Setups an account from the given information.
+/// The account is cached, and only set up the first time
+/// the same information is given.
 ///
 /// # Arguments
 ///
-/// * `url` - The RPC URL or network name. If it starts with "http", the
+/// * `url_network` - The RPC URL or network name. If it starts with "http", the
 ///   JSON RPC provider is used, else the gateway network if inferred.
 /// * `account_address` - Address of the deployed account.
-/// * `account_privkey` - Private key associated with the account to sign transactions.
+/// * `signer` - Signer associated with the account to sign transactions.
+/// * `is_legacy` - If the account expects the legacy (Cairo 0) execution encoding.
+/// * `chain_id` - Chain id to use instead of fetching it from the provider.
 pub async fn setup_account(
     url_network: &str,
     account_address: &str,
-    account_privkey: &str,
+    signer: KiptSigner,
     is_legacy: bool,
-) -> KiptResult<SingleOwnerAccount<AnyProvider, LocalWallet>> {
-    let provider = if url_network.starts_with("http") {
-        provider_from_url(url_network)?
-    } else {
-        provider_from_network(url_network)?
+    chain_id: Option<FieldElement>,
+) -> KiptResult<Arc<KiptAccount>> {
+    let key = AccountKey {
+        url_network: url_network.to_string(),
+        address: FieldElement::from_hex_be(account_address)?,
+        public_key: signer
+            .get_public_key()
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?
+            .scalar(),
+        is_legacy,
+        chain_id,
     };
 
-    let chain_id = provider.chain_id().await?;
-
-    let addr = FieldElement::from_hex_be(account_address)?;
-    let key = FieldElement::from_hex_be(account_privkey)?;
+    if let Some(account) = ACCOUNTS.lock().unwrap().get(&key) {
+        return Ok(Arc::clone(account));
+    }
 
-    let signer = LocalWallet::from(SigningKey::from_secret_scalar(key));
+    let provider = setup_provider(url_network).await?;
+    let chain_id = resolve_chain_id(url_network, &provider, chain_id).await?;
 
-    let account = if is_legacy {
-        SingleOwnerAccount::new(provider, signer, addr, chain_id, ExecutionEncoding::Legacy)
+    let encoding = if is_legacy {
+        ExecutionEncoding::Legacy
     } else {
-        SingleOwnerAccount::new(provider, signer, addr, chain_id, ExecutionEncoding::New)
+        ExecutionEncoding::New
     };
 
+    let account = Arc::new(SingleOwnerAccount::new(
+        provider,
+        signer,
+        key.address,
+        chain_id,
+        encoding,
+    ));
+
+    ACCOUNTS.lock().unwrap().insert(key, Arc::clone(&account));
+
     Ok(account)
 }
 
+/// Returns the chain id of the given network, only fetched
+/// from the provider the first time if it's not given.
+///
+/// # Arguments
+///
+/// * `url_network` - The RPC URL or network name.
+/// * `provider` - The provider of this network.
+/// * `chain_id` - Chain id from the `CHAIN_ID` global, returned as is if set.
+pub async fn resolve_chain_id(
+    url_network: &str,
+    provider: &AnyProvider,
+    chain_id: Option<FieldElement>,
+) -> KiptResult<FieldElement> {
+    if let Some(c) = chain_id {
+        return Ok(c);
+    }
+
+    if let Some(chain_id) = CHAIN_IDS.lock().unwrap().get(url_network) {
+        return Ok(*chain_id);
+    }
+
+    let chain_id = provider.chain_id().await?;
+    CHAIN_IDS
+        .lock()
+        .unwrap()
+        .insert(url_network.to_string(), chain_id);
+
+    Ok(chain_id)
+}
+
 /// Builds a provider from the given URL.
 ///
 /// # Arguments
@@ -101,3 +259,111 @@ fn provider_from_network(network: &str) -> KiptResult<AnyProvider> {
         _ => Err(Error::Other(format!("Invalid network: {}", network))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn felt(hex: &str) -> FieldElement {
+        FieldElement::from_hex_be(hex).unwrap()
+    }
+
+    fn descriptor(json: &str) -> AccountDescriptor {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn open_zeppelin_deployed() {
+        let d = descriptor(
+            r#"{
+                "version": 1,
+                "variant": { "type": "open_zeppelin", "version": 1, "public_key": "0x2", "legacy": false },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1234" }
+            }"#,
+        );
+
+        assert_eq!(d.address().unwrap(), felt("0x1234"));
+        assert!(!d.is_legacy());
+    }
+
+    #[test]
+    fn open_zeppelin_defaults_to_legacy() {
+        let d = descriptor(
+            r#"{
+                "version": 1,
+                "variant": { "type": "open_zeppelin", "version": 1, "public_key": "0x2" },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1234" }
+            }"#,
+        );
+
+        assert!(d.is_legacy());
+    }
+
+    #[test]
+    fn argent() {
+        let cairo_1 = descriptor(
+            r#"{
+                "version": 1,
+                "variant": { "type": "argent", "version": 1, "owner": "0x2", "guardian": "0x0" },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1234" }
+            }"#,
+        );
+        let cairo_0 = descriptor(
+            r#"{
+                "version": 1,
+                "variant": {
+                    "type": "argent", "version": 1, "implementation": "0x5",
+                    "signer": "0x2", "guardian": "0x0"
+                },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1234" }
+            }"#,
+        );
+
+        assert!(!cairo_1.is_legacy());
+        assert!(cairo_0.is_legacy());
+    }
+
+    #[test]
+    fn braavos() {
+        let d = descriptor(
+            r#"{
+                "version": 1,
+                "variant": {
+                    "type": "braavos", "version": 1, "implementation": "0x5",
+                    "multisig": { "status": "off" },
+                    "signers": [{ "type": "stark", "public_key": "0x2" }]
+                },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1234" }
+            }"#,
+        );
+
+        assert_eq!(d.address().unwrap(), felt("0x1234"));
+        assert!(d.is_legacy());
+    }
+
+    #[test]
+    fn undeployed_has_no_address() {
+        let d = descriptor(
+            r#"{
+                "version": 1,
+                "variant": { "type": "open_zeppelin", "version": 1, "public_key": "0x2" },
+                "deployment": { "status": "undeployed", "class_hash": "0x3", "salt": "0x4" }
+            }"#,
+        );
+
+        assert!(matches!(d.address(), Err(Error::Other(_))));
+    }
+
+    #[test]
+    fn unknown_variant_is_rejected() {
+        let d = serde_json::from_str::<AccountDescriptor>(
+            r#"{
+                "version": 1,
+                "variant": { "type": "unknown", "version": 1 },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1234" }
+            }"#,
+        );
+
+        assert!(d.is_err());
+    }
+}
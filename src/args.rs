@@ -1,12 +1,307 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
 
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Parser)]
 #[command(arg_required_else_help(true))]
 pub struct Args {
-    #[clap(help = "Path to lua program to be executed")]
+    #[clap(help = "Path to lua program to be executed, same as `kipt run`")]
     pub lua: Option<PathBuf>,
+    #[clap(
+        long = "dry-run",
+        global = true,
+        help = "Simulate the transactions and estimate fees without sending them"
+    )]
+    pub dry_run: bool,
+    #[clap(
+        long = "rpc",
+        global = true,
+        help = "RPC URL or network name, sets the RPC global"
+    )]
+    pub rpc: Option<String>,
+    #[clap(
+        long = "chain-id",
+        global = true,
+        help = "Chain id of the network, sets the CHAIN_ID global"
+    )]
+    pub chain_id: Option<String>,
+    #[clap(
+        long = "account-address",
+        global = true,
+        help = "Address of the account, sets the ACCOUNT_ADDRESS global"
+    )]
+    pub account_address: Option<String>,
+    #[clap(
+        long = "account-file",
+        global = true,
+        help = "Path to a starkli account file, sets the ACCOUNT_FILE global"
+    )]
+    pub account_file: Option<PathBuf>,
+    #[clap(
+        long = "private-key",
+        global = true,
+        help = "Private key of the account, sets the ACCOUNT_PRIVKEY global (prefer --keystore)"
+    )]
+    pub private_key: Option<String>,
+    #[clap(
+        long = "keystore",
+        global = true,
+        help = "Path to an encrypted keystore, sets the ACCOUNT_KEYSTORE global"
+    )]
+    pub keystore: Option<PathBuf>,
+    #[clap(
+        long = "profile",
+        global = true,
+        help = "Name of the kipt.toml profile used to set the globals"
+    )]
+    pub profile: Option<String>,
+    #[clap(
+        long = "export",
+        global = true,
+        help = "Path of a file where the signed transactions are written instead of being sent, sets the EXPORT_TX global"
+    )]
+    pub export: Option<PathBuf>,
+    #[clap(
+        long = "lib-path",
+        global = true,
+        help = "Directory of the Lua modules to require, lib next to the Lua program by default"
+    )]
+    pub lib_path: Option<PathBuf>,
+    #[clap(
+        long = "define",
+        short = 'D',
+        global = true,
+        value_name = "KEY=VALUE",
+        help = "Value exposed to the Lua program in the ARGS table, can be repeated"
+    )]
+    pub define: Vec<String>,
     #[clap(long = "version", short = 'V', help = "Print version info and exit")]
     pub version: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Options of the transactions sent from the command line.
+#[derive(Debug, Clone, clap::Args)]
+pub struct TxArgs {
+    #[clap(
+        long = "max-fee",
+        help = "Max fee of the transaction, estimated if not set"
+    )]
+    pub max_fee: Option<String>,
+    #[clap(long = "nonce", help = "Nonce of the transaction, fetched if not set")]
+    pub nonce: Option<String>,
+    #[clap(
+        long = "watch",
+        help = "Interval in milliseconds to poll the receipt, no watch if not set"
+    )]
+    pub watch_interval: Option<u32>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    #[clap(about = "Execute a lua program")]
+    Run {
+        #[clap(help = "Path to lua program to be executed")]
+        lua: PathBuf,
+        #[clap(
+            last = true,
+            help = "Arguments of the Lua program, given after --, exposed in the arg and ARGS tables"
+        )]
+        script_args: Vec<String>,
+    },
+    #[clap(about = "Start an interactive Lua console with the Starknet functions")]
+    Repl,
+    #[clap(about = "Call a function of a contract")]
+    Call {
+        #[clap(help = "Address of the contract")]
+        address: String,
+        #[clap(help = "Name of the function")]
+        function: String,
+        #[clap(help = "Arguments of the function")]
+        calldata: Vec<String>,
+        #[clap(long = "block-id", help = "Block id of the call, pending by default")]
+        block_id: Option<String>,
+        #[clap(
+            long = "abi",
+            help = "Name of the contract whose ABI is used to encode the arguments"
+        )]
+        abi: Option<String>,
+    },
+    #[clap(about = "Invoke a function of a contract")]
+    Invoke {
+        #[clap(help = "Address of the contract")]
+        address: String,
+        #[clap(help = "Name of the function")]
+        function: String,
+        #[clap(help = "Arguments of the function")]
+        calldata: Vec<String>,
+        #[clap(flatten)]
+        tx: TxArgs,
+    },
+    #[clap(about = "Declare a contract from its artifacts")]
+    Declare {
+        #[clap(help = "Name of the contract")]
+        contract: String,
+        #[clap(
+            long = "artifacts-path",
+            help = "Directory of the artifacts, ./target/dev by default"
+        )]
+        artifacts_path: Option<String>,
+        #[clap(
+            long = "skip-if-declared",
+            help = "Don't send the transaction if the class is already declared"
+        )]
+        skip_if_declared: bool,
+        #[clap(
+            long = "scarb-build",
+            help = "Run scarb build before the declaration if the artifacts are stale"
+        )]
+        scarb_build: bool,
+        #[clap(
+            long = "compile-casm",
+            help = "Compile the Sierra class to Casm if the Casm artifacts are missing"
+        )]
+        compile_casm: bool,
+        #[clap(flatten)]
+        tx: TxArgs,
+    },
+    #[clap(about = "Deploy a contract from its class hash")]
+    Deploy {
+        #[clap(help = "Class hash of the contract")]
+        class_hash: String,
+        #[clap(help = "Arguments of the constructor")]
+        args: Vec<String>,
+        #[clap(long = "salt", help = "Salt of the deployment, random if not set")]
+        salt: Option<String>,
+        #[clap(flatten)]
+        tx: TxArgs,
+    },
+    #[clap(about = "Print the status of a transaction")]
+    TxStatus {
+        #[clap(help = "Hash of the transaction")]
+        tx_hash: String,
+    },
+    #[clap(about = "Send the signed transactions of a file exported with --export")]
+    Broadcast {
+        #[clap(help = "Path of the file with the signed transactions")]
+        file: PathBuf,
+        #[clap(
+            long = "receipts",
+            help = "Path of the file where the receipts are written, <file>.receipts.json by default"
+        )]
+        receipts: Option<PathBuf>,
+        #[clap(
+            long = "watch-interval",
+            default_value_t = 1000,
+            help = "Interval in milliseconds to poll the receipts"
+        )]
+        watch_interval: u64,
+    },
+    #[clap(about = "Generate the shell completions")]
+    Completions {
+        #[clap(help = "Shell to generate the completions for")]
+        shell: Shell,
+    },
+}
+
+/// Parses the arguments of a Lua program into named values and positional values.
+/// `--key value` and `--key=value` are named values, and `--flag`
+/// without value is set to `true` (represented as `None`).
+///
+/// # Arguments
+///
+/// * `script_args` - The arguments given after `--`.
+pub fn parse_script_args(script_args: &[String]) -> (Vec<(String, Option<String>)>, Vec<String>) {
+    let mut named = vec![];
+    let mut positional = vec![];
+
+    let mut it = script_args.iter().peekable();
+    while let Some(a) = it.next() {
+        let Some(key) = a.strip_prefix("--") else {
+            positional.push(a.clone());
+            continue;
+        };
+
+        if let Some((k, v)) = key.split_once('=') {
+            named.push((k.to_string(), Some(v.to_string())));
+        } else if it.peek().is_some_and(|n| !n.starts_with("--")) {
+            named.push((key.to_string(), it.next().cloned()));
+        } else {
+            named.push((key.to_string(), None));
+        }
+    }
+
+    (named, positional)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> (Vec<(String, Option<String>)>, Vec<String>) {
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        parse_script_args(&args)
+    }
+
+    fn named(key: &str, value: Option<&str>) -> (String, Option<String>) {
+        (key.to_string(), value.map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn named_values() {
+        let (n, p) = parse(&["--network", "katana", "--salt=0x1234"]);
+
+        assert_eq!(
+            n,
+            vec![
+                named("network", Some("katana")),
+                named("salt", Some("0x1234"))
+            ]
+        );
+        assert!(p.is_empty());
+    }
+
+    #[test]
+    fn flags_without_value() {
+        let (n, p) = parse(&["--verbose", "--dry", "--name", "c1", "--last"]);
+
+        assert_eq!(
+            n,
+            vec![
+                named("verbose", None),
+                named("dry", None),
+                named("name", Some("c1")),
+                named("last", None),
+            ]
+        );
+        assert!(p.is_empty());
+    }
+
+    #[test]
+    fn positional_values() {
+        let (n, p) = parse(&["a", "--key", "v", "b", "c"]);
+
+        assert_eq!(n, vec![named("key", Some("v"))]);
+        assert_eq!(p, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn value_containing_equal_sign() {
+        let (n, _) = parse(&["--query=a=b", "--empty="]);
+
+        assert_eq!(
+            n,
+            vec![named("query", Some("a=b")), named("empty", Some(""))]
+        );
+    }
+
+    #[test]
+    fn no_arguments() {
+        let (n, p) = parse(&[]);
+
+        assert!(n.is_empty());
+        assert!(p.is_empty());
+    }
 }
@@ -0,0 +1,358 @@
+//! Index of the contract artifacts generated by Scarb,
+//! to locate the Sierra and Casm files of a contract from its name.
+//! The Cairo 0 contracts, compiled in a single `<name>.json` file, are also indexed.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::trace;
+
+use crate::error::{Error, KiptResult};
+use crate::scarb;
+
+/// Extensions of the Sierra artifacts.
+const SIERRA_EXTS: [&str; 2] = [".contract_class.json", "sierra.json"];
+
+/// Extensions of the Casm artifacts.
+const CASM_EXTS: [&str; 2] = [".compiled_contract_class.json", "casm.json"];
+
+/// Extension of the Cairo 0 compiled contracts.
+const LEGACY_EXT: &str = ".json";
+
+/// Artifacts of a contract, found in the same directory with the same file stem.
+#[derive(Debug, Default, Clone)]
+pub struct ContractArtifacts {
+    pub sierra_path: Option<PathBuf>,
+    pub casm_path: Option<PathBuf>,
+    /// A `<name>.json` file, which may be a Cairo 0 compiled contract.
+    pub legacy_path: Option<PathBuf>,
+}
+
+/// Artifacts located for a contract.
+#[derive(Debug, Clone)]
+pub enum LocatedArtifacts {
+    /// Cairo 1 contract, the Casm artifact being `None` if only the Sierra artifact is found.
+    Sierra {
+        sierra_path: String,
+        casm_path: Option<String>,
+    },
+    /// Cairo 0 contract, compiled in a single file.
+    Legacy { path: String },
+}
+
+/// All the contract artifacts found in a directory, indexed
+/// by the directory and the file stem (the name before the first `.`).
+#[derive(Debug, Default)]
+pub struct ArtifactIndex {
+    entries: BTreeMap<(PathBuf, String), ContractArtifacts>,
+}
+
+impl ArtifactIndex {
+    /// Builds the index of the artifacts of a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifacts_dir` - The directory where to search for the files.
+    /// * `is_recursive` - If the sub-directories are also indexed.
+    pub fn new(artifacts_dir: &str, is_recursive: bool) -> KiptResult<Self> {
+        let dir = PathBuf::from(artifacts_dir).canonicalize().map_err(|e| {
+            Error::ArtifactsMissing(format!(
+                "Artifacts directory {} can't be read: {}",
+                artifacts_dir, e
+            ))
+        })?;
+
+        trace!("Indexing artifacts in root path: {:?}", dir);
+
+        let mut index = Self::default();
+        index.index_dir(&dir, is_recursive)?;
+        Ok(index)
+    }
+
+    /// Adds the artifacts of a directory to the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to index.
+    /// * `is_recursive` - If the sub-directories are also indexed.
+    fn index_dir(&mut self, dir: &Path, is_recursive: bool) -> KiptResult<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry_path = entry?.path();
+
+            if entry_path.is_dir() {
+                if is_recursive {
+                    trace!("Recursive entry: {:?}", entry_path);
+                    self.index_dir(&entry_path, is_recursive)?;
+                }
+                continue;
+            }
+
+            let Some(file_name) = entry_path.file_name() else {
+                continue;
+            };
+
+            let fname = file_name.to_string_lossy();
+            let Some(stem) = fname.split('.').next() else {
+                continue;
+            };
+
+            let is_sierra = SIERRA_EXTS.iter().any(|ext| fname.ends_with(ext));
+            let is_casm = CASM_EXTS.iter().any(|ext| fname.ends_with(ext));
+            let is_legacy = fname == format!("{}{}", stem, LEGACY_EXT);
+            if !is_sierra && !is_casm && !is_legacy {
+                continue;
+            }
+
+            let artifacts = self
+                .entries
+                .entry((dir.to_path_buf(), stem.to_string()))
+                .or_default();
+
+            if is_sierra {
+                trace!("Sierra artifact found: {}", fname);
+                artifacts.sierra_path = Some(entry_path.clone());
+            } else if is_casm {
+                trace!("Casm artifact found: {}", fname);
+                artifacts.casm_path = Some(entry_path.clone());
+            } else {
+                trace!("Legacy artifact candidate found: {}", fname);
+                artifacts.legacy_path = Some(entry_path.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the artifacts of a contract: the Sierra and Casm artifacts, or
+    /// the Cairo 0 compiled contract if no Sierra artifact is found.
+    ///
+    /// The name is the file stem of the artifacts (`my_pkg_MyContract`), or the contract
+    /// name without the package prefix added by Scarb (`MyContract`).
+    /// A package-qualified name (`my_pkg::MyContract`) selects the artifacts of the package,
+    /// prefixed by the package name or located in a directory named after the package.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract_name` - Name of the contract.
+    pub fn locate(&self, contract_name: &str) -> KiptResult<LocatedArtifacts> {
+        let mut candidates = self.candidates(contract_name);
+
+        if candidates.len() > 1 {
+            let list = candidates
+                .iter()
+                .map(|((dir, stem), _)| format!("  {}", dir.join(stem).display()))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            return Err(Error::ArtifactsAmbiguous(format!(
+                "Several artifacts match the contract {}, use a package-qualified name \
+                 (package::Contract) or the full name of the artifacts:\n{}",
+                contract_name, list
+            )));
+        }
+
+        match candidates.pop() {
+            Some((
+                _,
+                ContractArtifacts {
+                    sierra_path: Some(s),
+                    casm_path,
+                    ..
+                },
+            )) => Ok(LocatedArtifacts::Sierra {
+                sierra_path: s.to_string_lossy().to_string(),
+                casm_path: casm_path.as_ref().map(|c| c.to_string_lossy().to_string()),
+            }),
+            Some((
+                _,
+                ContractArtifacts {
+                    legacy_path: Some(l),
+                    ..
+                },
+            )) => Ok(LocatedArtifacts::Legacy {
+                path: l.to_string_lossy().to_string(),
+            }),
+            _ => Err(Error::ArtifactsMissing(format!(
+                "Sierra artifacts not found for contract {}",
+                contract_name
+            ))),
+        }
+    }
+
+    /// Returns the artifacts matching the name of a contract. An exact match
+    /// of the file stem is preferred to a match without the package prefix,
+    /// and the Sierra artifacts are preferred to the Cairo 0 candidates.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract_name` - Name of the contract.
+    fn candidates(&self, contract_name: &str) -> Vec<(&(PathBuf, String), &ContractArtifacts)> {
+        let select = |f: &dyn Fn(&Path, &str) -> bool| {
+            let matching = self
+                .entries
+                .iter()
+                .filter(|((dir, stem), _)| f(dir, stem))
+                .collect::<Vec<_>>();
+
+            if matching.iter().any(|(_, a)| a.sierra_path.is_some()) {
+                matching
+                    .into_iter()
+                    .filter(|(_, a)| a.sierra_path.is_some())
+                    .collect()
+            } else {
+                matching
+            }
+        };
+
+        if let Some((package, contract)) = contract_name.split_once("::") {
+            let prefixed = format!("{}_{}", package, contract);
+            return select(&|dir, stem| {
+                stem == prefixed
+                    || (stem == contract
+                        && dir
+                            .file_name()
+                            .is_some_and(|d| d.to_string_lossy() == package))
+            });
+        }
+
+        let exact = select(&|_, stem| stem == contract_name);
+        if !exact.is_empty() {
+            return exact;
+        }
+
+        let suffix = format!("_{}", contract_name);
+        select(&|_, stem| stem.ends_with(&suffix))
+    }
+}
+
+/// Locates the artifacts of a contract from its name. The Scarb artifacts manifests
+/// are used first, else the artifacts are searched by their file names.
+/// If no Sierra artifact is found, a Cairo 0 compiled contract may be returned.
+///
+/// # Arguments
+///
+/// * `contract_name` - Name of the contract, optionally qualified with its package (`pkg::Contract`)
+///   or given by its module path.
+/// * `artifacts_dir` - The directory where to search for the files.
+/// * `is_recursive` - If the search must be done recursively.
+pub fn locate_artifacts(
+    contract_name: &str,
+    artifacts_dir: &str,
+    is_recursive: bool,
+) -> KiptResult<LocatedArtifacts> {
+    match scarb::locate_artifacts(contract_name, Path::new(artifacts_dir))? {
+        Some((sierra_path, casm_path)) => Ok(LocatedArtifacts::Sierra {
+            sierra_path: sierra_path.to_string_lossy().to_string(),
+            casm_path: casm_path.map(|c| c.to_string_lossy().to_string()),
+        }),
+        None => ArtifactIndex::new(artifacts_dir, is_recursive)?.locate(contract_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an index from `(dir, stem, has_sierra, has_legacy)` entries.
+    fn index(entries: &[(&str, &str, bool, bool)]) -> ArtifactIndex {
+        let mut index = ArtifactIndex::default();
+
+        for (dir, stem, has_sierra, has_legacy) in entries {
+            let dir = PathBuf::from(dir);
+            let path = |ext: &str| dir.join(format!("{}{}", stem, ext));
+
+            index.entries.insert(
+                (dir.clone(), stem.to_string()),
+                ContractArtifacts {
+                    sierra_path: has_sierra.then(|| path(SIERRA_EXTS[0])),
+                    casm_path: has_sierra.then(|| path(CASM_EXTS[0])),
+                    legacy_path: has_legacy.then(|| path(LEGACY_EXT)),
+                },
+            );
+        }
+
+        index
+    }
+
+    fn stems(index: &ArtifactIndex, name: &str) -> Vec<String> {
+        index
+            .candidates(name)
+            .iter()
+            .map(|((dir, stem), _)| dir.join(stem).to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn exact_stem_is_preferred() {
+        let index = index(&[
+            ("/t", "token", true, false),
+            ("/t", "pkg_token", true, false),
+        ]);
+
+        assert_eq!(stems(&index, "token"), vec!["/t/token"]);
+        assert_eq!(stems(&index, "pkg_token"), vec!["/t/pkg_token"]);
+    }
+
+    #[test]
+    fn package_prefix_can_be_omitted() {
+        let index = index(&[("/t", "pkg_MyContract", true, false)]);
+
+        assert_eq!(stems(&index, "MyContract"), vec!["/t/pkg_MyContract"]);
+        assert!(stems(&index, "Contract").is_empty());
+        assert!(stems(&index, "Other").is_empty());
+    }
+
+    #[test]
+    fn same_contract_in_several_packages_is_ambiguous() {
+        let index = index(&[
+            ("/t", "a_Token", true, false),
+            ("/t", "b_Token", true, false),
+        ]);
+
+        assert_eq!(stems(&index, "Token").len(), 2);
+        assert!(matches!(
+            index.locate("Token"),
+            Err(Error::ArtifactsAmbiguous(_))
+        ));
+    }
+
+    #[test]
+    fn package_qualified_name() {
+        let index = index(&[
+            ("/t", "a_Token", true, false),
+            ("/t", "b_Token", true, false),
+            ("/t/c", "Token", true, false),
+        ]);
+
+        assert_eq!(stems(&index, "a::Token"), vec!["/t/a_Token"]);
+        assert_eq!(stems(&index, "c::Token"), vec!["/t/c/Token"]);
+        assert!(stems(&index, "d::Token").is_empty());
+    }
+
+    #[test]
+    fn sierra_is_preferred_to_legacy() {
+        let index = index(&[
+            ("/t", "Proxy", false, true),
+            ("/t/sub", "Proxy", true, false),
+        ]);
+
+        assert_eq!(stems(&index, "Proxy"), vec!["/t/sub/Proxy"]);
+        assert!(matches!(
+            index.locate("Proxy"),
+            Ok(LocatedArtifacts::Sierra { .. })
+        ));
+    }
+
+    #[test]
+    fn legacy_only() {
+        let index = index(&[("/t", "Proxy", false, true)]);
+
+        assert!(matches!(
+            index.locate("Proxy"),
+            Ok(LocatedArtifacts::Legacy { .. })
+        ));
+        assert!(matches!(
+            index.locate("Other"),
+            Err(Error::ArtifactsMissing(_))
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+//! Broadcast of the signed transactions exported to a file
+//! (see `EXPORT_TX`), recording their receipts.
+use starknet::{
+    core::types::{BroadcastedTransaction, ExecutionResult, FieldElement},
+    providers::{AnyProvider, Provider},
+};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::account;
+use crate::error::{Error, KiptResult};
+use crate::transaction;
+
+/// Sends the transactions of the given file one after the other, waiting
+/// for the receipt of each transaction before sending the next one.
+/// The receipts are written to the receipts file as they are received.
+/// Stops at the first transaction failing or reverting.
+///
+/// # Arguments
+///
+/// * `file` - Path of the file with the signed transactions.
+/// * `url_network` - The RPC URL or network name.
+/// * `receipts_file` - Path of the file where the receipts are written.
+/// * `poll_interval` - Interval for the polling of the receipts.
+pub async fn broadcast(
+    file: &Path,
+    url_network: &str,
+    receipts_file: &Path,
+    poll_interval: Duration,
+) -> KiptResult<()> {
+    let content = fs::read_to_string(file).map_err(|e| {
+        Error::Other(format!(
+            "Transactions file can't be read {}: {}",
+            file.display(),
+            e
+        ))
+    })?;
+
+    let transactions: Vec<BroadcastedTransaction> = serde_json::from_str(&content)?;
+    let provider = account::setup_provider(url_network).await?;
+
+    let mut receipts = vec![];
+
+    for (i, tx) in transactions.iter().enumerate() {
+        let transaction_hash = send(&provider, tx).await?;
+        println!(
+            "[{}/{}] sent 0x{:064x}",
+            i + 1,
+            transactions.len(),
+            transaction_hash
+        );
+
+        let receipt = transaction::poll_receipt(&provider, transaction_hash, poll_interval).await?;
+        let result = receipt.execution_result().clone();
+
+        receipts.push(serde_json::to_value(&receipt)?);
+        fs::write(receipts_file, serde_json::to_string_pretty(&receipts)?)?;
+
+        if let ExecutionResult::Reverted { reason } = result {
+            return Err(Error::Reverted {
+                transaction_hash,
+                reason,
+            });
+        }
+    }
+
+    println!(
+        "{} transaction(s) executed, receipts written to {}",
+        transactions.len(),
+        receipts_file.display()
+    );
+
+    Ok(())
+}
+
+/// Sends a signed transaction.
+///
+/// # Arguments
+///
+/// * `provider` - The provider to send the transaction to.
+/// * `transaction` - The signed transaction.
+async fn send(
+    provider: &AnyProvider,
+    transaction: &BroadcastedTransaction,
+) -> KiptResult<FieldElement> {
+    let transaction_hash = match transaction {
+        BroadcastedTransaction::Invoke(t) => {
+            provider.add_invoke_transaction(t).await?.transaction_hash
+        }
+        BroadcastedTransaction::Declare(t) => {
+            provider.add_declare_transaction(t).await?.transaction_hash
+        }
+        // Account deployments are never exported (see `deploy_account`).
+        BroadcastedTransaction::DeployAccount(_) => {
+            return Err(Error::Other(
+                "Account deployments can't be broadcast, only invoke and declare transactions are exported".to_string(),
+            ))
+        }
+    };
+
+    Ok(transaction_hash)
+}
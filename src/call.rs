@@ -1,4 +1,4 @@
-use mlua::{Error as LuaError, Lua, Result as LuaResult, Table};
+use mlua::{Lua, Result as LuaResult, Table};
 use regex::Regex;
 use starknet::{
     core::types::{BlockId, BlockTag, FieldElement, FunctionCall},
@@ -6,18 +6,24 @@ use starknet::{
     providers::{AnyProvider, Provider},
 };
 
+use crate::abi::ContractAbi;
 use crate::account;
-use crate::error::{Error, ErrorExtLua, KiptResult};
+use crate::error::{Error, KiptResult};
 use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
 
 /// Call output.
 struct CallOutput {
-    pub data: Vec<String>,
+    pub data: Vec<FieldElement>,
+    /// The ABI used to decode the data, if decoding was requested.
+    pub abi: Option<ContractAbi>,
 }
 
 impl LuaTableSetable for CallOutput {
     fn set_all(&self, table: &Table) {
-        table.set("data", self.data.clone()).unwrap();
+        // Lua idx starts to 1, sadly.
+        for (idx, v) in (1..).zip(&self.data) {
+            table.set(idx, format!("0x{:064x}", v)).unwrap();
+        }
     }
 }
 
@@ -28,18 +34,31 @@ impl LuaTableSetable for CallOutput {
 /// * `lua` - Lua VM instance.
 /// * `contract_address` - The contract deployed contract address to call.
 /// * `function_name` - The function name to convert into a selector.
-/// * `calldata` - The function call arguments.
+/// * `calldata` - The function call arguments, encoded using the contract ABI
+///   if the "abi" option is given.
 /// * `options` - Options for the call.
 pub fn lua_call<'lua>(
     lua: &'lua Lua,
     contract_address: String,
     function_name: String,
-    calldata: Vec<String>,
+    calldata: Table<'lua>,
     options: Table<'lua>,
 ) -> LuaResult<Table<'lua>> {
     let url_network = lua::get_provider(lua)?;
 
+    let abi = lua::get_abi_from_options(lua, &options)?;
+    let calldata = lua::encode_args(
+        abi.as_ref(),
+        |abi| abi.function_inputs(&function_name),
+        &calldata,
+    )?;
+
     let block_id: Option<String> = options.get("block_id")?;
+    let block_id = block_id.unwrap_or("pending".to_string());
+    let decode: bool = options.get("decode")?;
+    let short_strings: bool = options.get("short_strings")?;
+
+    let outputs_function_name = function_name.clone();
 
     let data = futures::executor::block_on(async move {
         RT.spawn(async move {
@@ -48,28 +67,50 @@ pub fn lua_call<'lua>(
                 Err(e) => {
                     return LuaOutput {
                         data: None,
-                        error: format!("{:?}", e),
+                        error: Some(e),
                     }
                 }
             };
 
-            match function_call(
+            let call_res = match function_call(
                 &provider,
                 &contract_address,
                 &function_name,
                 calldata,
-                &block_id.unwrap_or("pending".to_string()),
+                &block_id,
             )
             .await
             {
-                Ok(call_res) => LuaOutput {
-                    data: Some(CallOutput { data: call_res }),
-                    error: "".to_string(),
-                },
-                Err(e) => LuaOutput {
-                    data: None,
-                    error: format!("{:?}", e),
+                Ok(r) => r,
+                Err(e) => {
+                    return LuaOutput {
+                        data: None,
+                        error: Some(e),
+                    }
+                }
+            };
+
+            // The ABI is fetched from the chain only if not found locally.
+            let abi = match (decode, abi) {
+                (false, _) => None,
+                (true, Some(abi)) => Some(abi),
+                (true, None) => match fetch_abi(&provider, &contract_address, &block_id).await {
+                    Ok(abi) => Some(abi),
+                    Err(e) => {
+                        return LuaOutput {
+                            data: None,
+                            error: Some(e),
+                        }
+                    }
                 },
+            };
+
+            LuaOutput {
+                data: Some(CallOutput {
+                    data: call_res,
+                    abi,
+                }),
+                error: None,
             }
         })
         .await
@@ -77,20 +118,16 @@ pub fn lua_call<'lua>(
     });
 
     if let Some(d) = data.data {
-        let t = lua.create_table()?;
-
-        // Lua idx starts to 1, sadly.
-        let mut idx = 1;
-        for v in d.data {
-            t.set(idx, v)?;
-            idx += 1;
+        if let Some(abi) = &d.abi {
+            let outputs = abi.function_outputs(&outputs_function_name)?;
+            Ok(abi.decode_outputs(lua, outputs, &d.data, short_strings)?)
+        } else {
+            let t = lua.create_table()?;
+            d.set_all(&t);
+            Ok(t)
         }
-
-        Ok(t)
     } else {
-        Err(LuaError::ExternalError(std::sync::Arc::new(
-            ErrorExtLua::new(&data.error),
-        )))
+        Err(lua::output_error(data.error))
     }
 }
 
@@ -109,7 +146,7 @@ async fn function_call(
     function_name: &str,
     calldata: Vec<String>,
     block_id: &str,
-) -> KiptResult<Vec<String>> {
+) -> KiptResult<Vec<FieldElement>> {
     let mut sn_calldata = vec![];
 
     for c in calldata {
@@ -127,7 +164,27 @@ async fn function_call(
         )
         .await?;
 
-    Ok(r.into_iter().map(|f| format!("0x{:064x}", f)).collect())
+    Ok(r)
+}
+
+/// Fetches the ABI of a deployed contract.
+///
+/// # Arguments
+///
+/// * `provider` - The provider to fetch the contract class from.
+/// * `contract_address` - The deployed contract address.
+/// * `block_id` - The block id against which the class is fetched.
+async fn fetch_abi(
+    provider: &AnyProvider,
+    contract_address: &str,
+    block_id: &str,
+) -> KiptResult<ContractAbi> {
+    ContractAbi::from_contract(
+        provider,
+        FieldElement::from_hex_be(contract_address)?,
+        parse_block_id(block_id)?,
+    )
+    .await
 }
 
 /// Function from starkli: https://github.com/xJonathanLEI/starkli/blob/3fd85cf58f7adf757f3a62a86ae4e1fe487b4c8a/src/utils.rs#L59C1-L71C2
@@ -0,0 +1,73 @@
+//! Local compilation of the Sierra classes to Casm, to declare
+//! the contracts whose Casm artifacts are not available.
+//!
+//! The compiler is only embedded with the `sierra-compile` feature. Only Cairo 2.6.4
+//! is supported, pinned by the `cairo-lang-starknet-classes` dependency.
+use starknet::core::types::FieldElement;
+
+use crate::error::{Error, KiptResult};
+
+#[cfg(feature = "sierra-compile-2_6")]
+use cairo_lang_starknet_classes_2_6 as cairo_lang_starknet_classes;
+
+/// Max size of the bytecode of a compiled class accepted by Starknet.
+#[cfg(feature = "sierra-compile-2_6")]
+const MAX_BYTECODE_SIZE: usize = 180_000;
+
+/// Returns the version of the embedded compiler.
+#[cfg(feature = "sierra-compile-2_6")]
+fn compiler_version() -> String {
+    cairo_lang_starknet_classes::compiler_version::current_compiler_version_id().to_string()
+}
+
+/// Compiles a Sierra class to Casm and returns the hash of the compiled class.
+///
+/// # Arguments
+///
+/// * `sierra_json` - Content of the Sierra artifact.
+#[cfg(feature = "sierra-compile-2_6")]
+pub fn compiled_class_hash(sierra_json: &str) -> KiptResult<FieldElement> {
+    use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
+    use cairo_lang_starknet_classes::contract_class::ContractClass;
+    use tracing::trace;
+
+    let version = compiler_version();
+
+    trace!("Compiling Sierra to Casm with compiler {}", version);
+
+    let contract_class: ContractClass = serde_json::from_str(sierra_json).map_err(|e| {
+        Error::Other(format!(
+            "Sierra class can't be read by compiler {}: {}",
+            version, e
+        ))
+    })?;
+
+    let casm_class =
+        CasmContractClass::from_contract_class(contract_class, false, MAX_BYTECODE_SIZE).map_err(
+            |e| {
+                Error::Other(format!(
+                    "Sierra can't be compiled to Casm with compiler {}: {}",
+                    version, e
+                ))
+            },
+        )?;
+
+    let hash = casm_class.compiled_class_hash().to_be_bytes();
+
+    FieldElement::from_bytes_be(&hash).map_err(|e| Error::Other(e.to_string()))
+}
+
+/// Compiles a Sierra class to Casm and returns the hash of the compiled class.
+/// Always fails, as kipt was built without the `sierra-compile` feature.
+///
+/// # Arguments
+///
+/// * `sierra_json` - Content of the Sierra artifact.
+#[cfg(not(feature = "sierra-compile-2_6"))]
+pub fn compiled_class_hash(_sierra_json: &str) -> KiptResult<FieldElement> {
+    Err(Error::Other(
+        "Casm can't be compiled, kipt must be built with the `sierra-compile` feature \
+         (Cairo 2.6.4 compiler)"
+            .to_string(),
+    ))
+}
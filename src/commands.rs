@@ -0,0 +1,205 @@
+//! One-off commands, running the same functions as the Lua
+//! programs without having to write a Lua file.
+use mlua::{Lua, Result as LuaResult, Table, Value};
+
+use crate::args::TxArgs;
+use crate::{call, declare, deploy, invoke, transaction};
+
+/// Calls a function of a contract and prints the returned values.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance, set up from the arguments and the profile.
+/// * `address` - Address of the contract.
+/// * `function` - Name of the function.
+/// * `calldata` - Arguments of the function.
+/// * `block_id` - Block id of the call.
+/// * `abi` - Name of the contract whose ABI is used to encode the arguments.
+pub fn call(
+    lua: &Lua,
+    address: &str,
+    function: &str,
+    calldata: &[String],
+    block_id: Option<&str>,
+    abi: Option<&str>,
+) -> LuaResult<()> {
+    let options = lua.create_table()?;
+    options.set("block_id", block_id)?;
+    options.set("abi", abi)?;
+
+    let t = call::lua_call(
+        lua,
+        address.to_string(),
+        function.to_string(),
+        lua.create_sequence_from(calldata.iter().cloned())?,
+        options,
+    )?;
+
+    print_table(&t)
+}
+
+/// Invokes a function of a contract and prints the transaction outcome.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance, set up from the arguments and the profile.
+/// * `address` - Address of the contract.
+/// * `function` - Name of the function.
+/// * `calldata` - Arguments of the function.
+/// * `tx` - Options of the transaction.
+pub fn invoke(
+    lua: &Lua,
+    address: &str,
+    function: &str,
+    calldata: &[String],
+    tx: &TxArgs,
+) -> LuaResult<()> {
+    let c = lua.create_table()?;
+    c.set("to", address)?;
+    c.set("func", function)?;
+    c.set(
+        "calldata",
+        lua.create_sequence_from(calldata.iter().cloned())?,
+    )?;
+
+    let t = invoke::lua_invoke(lua, vec![c], tx_options(lua, tx)?)?;
+
+    print_table(&t)
+}
+
+/// Declares a contract and prints the transaction outcome.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance, set up from the arguments and the profile.
+/// * `contract` - Name of the contract.
+/// * `artifacts_path` - Directory of the artifacts.
+/// * `skip_if_declared` - If the declaration is skipped when the class is already declared.
+/// * `scarb_build` - If scarb build is run when the artifacts are stale.
+/// * `compile_casm` - If the Sierra class is compiled when the Casm artifacts are missing.
+/// * `tx` - Options of the transaction.
+pub fn declare(
+    lua: &Lua,
+    contract: &str,
+    artifacts_path: Option<&str>,
+    skip_if_declared: bool,
+    scarb_build: bool,
+    compile_casm: bool,
+    tx: &TxArgs,
+) -> LuaResult<()> {
+    let options = tx_options(lua, tx)?;
+    options.set("artifacts_path", artifacts_path)?;
+    options.set("skip_if_declared", skip_if_declared)?;
+    if scarb_build {
+        options.set("scarb_build", true)?;
+    }
+    if compile_casm {
+        options.set("compile_casm", true)?;
+    }
+
+    let t = declare::lua_declare(lua, contract.to_string(), options)?;
+
+    print_table(&t)
+}
+
+/// Deploys a contract and prints the transaction outcome.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance, set up from the arguments and the profile.
+/// * `class_hash` - Class hash of the contract.
+/// * `ctor_args` - Arguments of the constructor.
+/// * `salt` - Salt of the deployment.
+/// * `tx` - Options of the transaction.
+pub fn deploy(
+    lua: &Lua,
+    class_hash: &str,
+    ctor_args: &[String],
+    salt: Option<&str>,
+    tx: &TxArgs,
+) -> LuaResult<()> {
+    let options = tx_options(lua, tx)?;
+    options.set("salt", salt)?;
+
+    let t = deploy::lua_deploy(
+        lua,
+        class_hash.to_string(),
+        lua.create_sequence_from(ctor_args.iter().cloned())?,
+        options,
+    )?;
+
+    print_table(&t)
+}
+
+/// Prints the status of a transaction.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance, set up from the arguments and the profile.
+/// * `tx_hash` - Hash of the transaction.
+pub fn tx_status(lua: &Lua, tx_hash: &str) -> LuaResult<()> {
+    let t = transaction::lua_tx_status(lua, tx_hash.to_string())?;
+
+    print_table(&t)
+}
+
+/// Builds the options table of a transaction from the command line.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `tx` - Options of the transaction.
+fn tx_options<'lua>(lua: &'lua Lua, tx: &TxArgs) -> LuaResult<Table<'lua>> {
+    let options = lua.create_table()?;
+    options.set("max_fee", tx.max_fee.as_deref())?;
+    options.set("nonce", tx.nonce.as_deref())?;
+    options.set("watch_interval", tx.watch_interval)?;
+
+    Ok(options)
+}
+
+/// Prints a table returned by a function: one value per line
+/// for a sequence, else one `key: value` per line, sorted by key.
+///
+/// # Arguments
+///
+/// * `table` - The table to print.
+fn print_table(table: &Table) -> LuaResult<()> {
+    if table.raw_len() > 0 {
+        for v in table.clone().sequence_values::<Value>() {
+            println!("{}", value_to_string(&v?));
+        }
+
+        return Ok(());
+    }
+
+    let mut entries = vec![];
+    for pair in table.clone().pairs::<String, Value>() {
+        let (k, v) = pair?;
+        entries.push((k, value_to_string(&v)));
+    }
+
+    entries.sort();
+
+    for (k, v) in entries {
+        println!("{}: {}", k, v);
+    }
+
+    Ok(())
+}
+
+/// Formats a Lua value to be printed.
+///
+/// # Arguments
+///
+/// * `value` - The value to format.
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string_lossy().to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Nil => "nil".to_string(),
+        v => format!("<{}>", v.type_name()),
+    }
+}
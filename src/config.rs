@@ -0,0 +1,232 @@
+//! Kipt configuration file (`kipt.toml`), defining named profiles
+//! used to pre-populate the Lua globals before the program is executed.
+//!
+//! The file is first searched in the current directory, and then
+//! in the user configuration directory (`$XDG_CONFIG_HOME/kipt/kipt.toml`
+//! or `~/.config/kipt/kipt.toml`).
+use mlua::{Lua, Result as LuaResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, KiptResult};
+
+/// Name of the configuration file.
+const CONFIG_FILE: &str = "kipt.toml";
+
+/// Name of the profile used when no profile is given.
+const DEFAULT_PROFILE: &str = "default";
+
+/// Content of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// A named profile, each field being the value of the global
+/// with the same name in upper case.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub rpc: Option<String>,
+    pub chain_id: Option<String>,
+    pub account_address: Option<String>,
+    pub account_privkey: Option<String>,
+    pub account_keystore: Option<String>,
+    pub account_file: Option<String>,
+    pub account_is_legacy: Option<bool>,
+    pub artifacts_path: Option<String>,
+    pub watch_interval: Option<u32>,
+    pub scarb_build: Option<bool>,
+    pub compile_casm: Option<bool>,
+    /// Directory of the Lua modules, not a global as it's used before the program runs.
+    pub lib_path: Option<String>,
+}
+
+impl Config {
+    /// Loads a configuration from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the configuration file.
+    pub fn load(path: &Path) -> KiptResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::Other(format!(
+                "Config file can't be read {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        toml::from_str(&content).map_err(|e| {
+            Error::Other(format!(
+                "Config file can't be parsed {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+impl Profile {
+    /// Sets the globals defined by the profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - Lua VM instance.
+    pub fn set_globals(&self, lua: &Lua) -> LuaResult<()> {
+        let globals = lua.globals();
+
+        globals.set("RPC", self.rpc.clone())?;
+        globals.set("CHAIN_ID", self.chain_id.clone())?;
+        globals.set("ACCOUNT_ADDRESS", self.account_address.clone())?;
+        globals.set("ACCOUNT_PRIVKEY", self.account_privkey.clone())?;
+        globals.set("ACCOUNT_KEYSTORE", self.account_keystore.clone())?;
+        globals.set("ACCOUNT_FILE", self.account_file.clone())?;
+        globals.set("ACCOUNT_IS_LEGACY", self.account_is_legacy)?;
+        globals.set("ARTIFACTS_PATH", self.artifacts_path.clone())?;
+        globals.set("WATCH_INTERVAL", self.watch_interval)?;
+        globals.set("SCARB_BUILD", self.scarb_build)?;
+        globals.set("COMPILE_CASM", self.compile_casm)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the paths where the configuration file is searched, by priority.
+fn config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(CONFIG_FILE)];
+
+    let user_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+
+    if let Some(dir) = user_dir {
+        paths.push(dir.join("kipt").join(CONFIG_FILE));
+    }
+
+    paths
+}
+
+/// Resolves the profile to be used.
+///
+/// The profile is taken from the first configuration file defining it.
+/// If no name is given, the `default` profile is used if any.
+///
+/// # Arguments
+///
+/// * `name` - Name of the profile, given with `--profile`.
+pub fn load_profile(name: Option<&str>) -> KiptResult<Option<Profile>> {
+    find_profile(&config_paths(), name)
+}
+
+/// Returns the profile from the first of the given configuration files defining it.
+///
+/// # Arguments
+///
+/// * `paths` - Paths of the configuration files, by priority.
+/// * `name` - Name of the profile, the `default` profile if `None`.
+fn find_profile(paths: &[PathBuf], name: Option<&str>) -> KiptResult<Option<Profile>> {
+    let lookup = name.unwrap_or(DEFAULT_PROFILE);
+
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let config = Config::load(path)?;
+        if let Some(profile) = config.profiles.get(lookup) {
+            return Ok(Some(profile.clone()));
+        }
+    }
+
+    match name {
+        Some(n) => Err(Error::Other(format!(
+            "Profile `{}` not found in any {} file",
+            n, CONFIG_FILE
+        ))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a configuration file unique to the test.
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("kipt-config-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    const CONFIG: &str = r#"
+        [profiles.default]
+        rpc = "KATANA"
+
+        [profiles.testnet]
+        rpc = "GOERLI-1"
+        account_address = "0x1234"
+        watch_interval = 300
+    "#;
+
+    #[test]
+    fn named_profile() {
+        let path = config_file("named", CONFIG);
+
+        let profile = find_profile(std::slice::from_ref(&path), Some("testnet"));
+        fs::remove_file(&path).unwrap();
+
+        let profile = profile.unwrap().unwrap();
+        assert_eq!(profile.rpc.as_deref(), Some("GOERLI-1"));
+        assert_eq!(profile.account_address.as_deref(), Some("0x1234"));
+        assert_eq!(profile.watch_interval, Some(300));
+    }
+
+    #[test]
+    fn default_profile() {
+        let path = config_file("default", CONFIG);
+
+        let profile = find_profile(std::slice::from_ref(&path), None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(profile.unwrap().unwrap().rpc.as_deref(), Some("KATANA"));
+    }
+
+    #[test]
+    fn missing_profile() {
+        let path = config_file("missing", "[profiles.testnet]\nrpc = \"GOERLI-1\"\n");
+        let not_found = std::env::temp_dir().join("kipt-config-not-found.toml");
+
+        let named = find_profile(&[path.clone(), not_found.clone()], Some("mainnet"));
+        let default = find_profile(&[path.clone(), not_found], None);
+        fs::remove_file(&path).unwrap();
+
+        assert!(named.is_err());
+        assert!(default.unwrap().is_none());
+    }
+
+    #[test]
+    fn first_file_defining_the_profile_is_used() {
+        let local = config_file("local", "[profiles.testnet]\nrpc = \"http://local\"\n");
+        let user = config_file("user", CONFIG);
+
+        let testnet = find_profile(&[local.clone(), user.clone()], Some("testnet"));
+        let default = find_profile(&[local.clone(), user.clone()], None);
+        fs::remove_file(&local).unwrap();
+        fs::remove_file(&user).unwrap();
+
+        assert_eq!(
+            testnet.unwrap().unwrap().rpc.as_deref(),
+            Some("http://local")
+        );
+        assert_eq!(default.unwrap().unwrap().rpc.as_deref(), Some("KATANA"));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(toml::from_str::<Config>("[profiles.default]\nrcp = \"KATANA\"\n").is_err());
+        assert!(toml::from_str::<Config>("[profile.default]\nrpc = \"KATANA\"\n").is_err());
+        assert!(toml::from_str::<Config>("[profiles.default]\nrpc = \"KATANA\"\n").is_ok());
+    }
+}
@@ -1,115 +1,310 @@
-use mlua::{Error as LuaError, Lua, Result as LuaResult, Table};
+use async_trait::async_trait;
+use mlua::{Lua, Result as LuaResult, Table};
 use starknet::{
-    accounts::{Account, ConnectedAccount, SingleOwnerAccount},
+    accounts::{Account, AccountError, ConnectedAccount, Declaration, LegacyDeclaration},
     core::types::{
-        contract::{CompiledClass, SierraClass},
-        BlockId, BlockTag, DeclareTransactionResult, FieldElement, StarknetError,
+        contract::{legacy::LegacyContractClass, CompiledClass, SierraClass},
+        BlockId, BlockTag, FieldElement, StarknetError,
     },
-    providers::{
-        AnyProvider, MaybeUnknownErrorCode, Provider, ProviderError, StarknetErrorWithMessage,
-    },
-    signers::LocalWallet,
+    providers::{MaybeUnknownErrorCode, Provider, ProviderError, StarknetErrorWithMessage},
 };
-use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
 use tracing::trace;
 
-use crate::error::{Error, ErrorExtLua, KiptResult};
+use crate::account::KiptAccount;
+use crate::artifacts::{self, LocatedArtifacts};
+use crate::error::{Error, KiptResult};
+use crate::export::Exportable;
 use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
-use crate::{account, logger, transaction};
+use crate::transaction::{TxOptions, TxOutcome};
+use crate::{account, casm, export, fee, logger, nonce, scarb, state, transaction};
 
 /// Declare output.
 struct DeclareOutput {
-    pub transaction_hash: Option<String>,
-    pub sierra_class_hash: String,
+    /// `None` if the declaration was skipped.
+    pub transaction: Option<TxOutcome>,
+    pub class_hash: String,
 }
 
 impl LuaTableSetable for DeclareOutput {
     fn set_all(&self, table: &Table) {
-        table.set("tx_hash", self.transaction_hash.clone()).unwrap();
+        if let Some(tx) = &self.transaction {
+            tx.set_all(table);
+        }
+
+        table.set("class_hash", self.class_hash.clone()).unwrap();
+    }
+}
+
+/// Source of a class to declare.
+enum ClassSource {
+    /// Artifacts located from the name of the contract.
+    Name {
+        contract_name: String,
+        artifacts_path: String,
+        is_recursive: bool,
+        scarb_build: bool,
+    },
+    /// Artifacts given by their paths.
+    Paths {
+        sierra_path: String,
+        casm_path: Option<String>,
+    },
+    /// Classes given as JSON strings.
+    Json {
+        sierra: String,
+        casm: Option<String>,
+    },
+}
+
+/// Class to declare, loaded from its source.
+enum ClassData {
+    /// Sierra class, with the Casm class if available.
+    Sierra {
+        sierra_json: String,
+        casm_json: Option<String>,
+    },
+    /// Cairo 0 compiled contract.
+    Legacy { json: String },
+}
+
+impl ClassSource {
+    /// Loads the class to declare, reading the artifacts files if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `compile_casm` - If the Sierra class can be declared without the Casm class,
+    ///   the Sierra class being compiled.
+    fn load(self, compile_casm: bool) -> KiptResult<ClassData> {
+        let (data, name) = match self {
+            ClassSource::Name {
+                contract_name,
+                artifacts_path,
+                is_recursive,
+                scarb_build,
+            } => {
+                if scarb_build {
+                    scarb::build_if_stale(Path::new(&artifacts_path))?;
+                }
+
+                let data = match artifacts::locate_artifacts(
+                    &contract_name,
+                    &artifacts_path,
+                    is_recursive,
+                )? {
+                    LocatedArtifacts::Sierra {
+                        sierra_path,
+                        casm_path,
+                    } => ClassData::Sierra {
+                        sierra_json: read_artifact(&sierra_path)?,
+                        casm_json: casm_path.map(|c| read_artifact(&c)).transpose()?,
+                    },
+                    LocatedArtifacts::Legacy { path } => ClassData::Legacy {
+                        json: read_artifact(&path)?,
+                    },
+                };
+
+                (data, format!("contract {}", contract_name))
+            }
+            ClassSource::Paths {
+                sierra_path,
+                casm_path,
+            } => {
+                let sierra_json = read_artifact(&sierra_path)?;
+                let data = if is_legacy_class(&sierra_json) {
+                    ClassData::Legacy { json: sierra_json }
+                } else {
+                    ClassData::Sierra {
+                        sierra_json,
+                        casm_json: casm_path.map(|c| read_artifact(&c)).transpose()?,
+                    }
+                };
+
+                (data, sierra_path)
+            }
+            ClassSource::Json { sierra, casm } => {
+                let data = if is_legacy_class(&sierra) {
+                    ClassData::Legacy { json: sierra }
+                } else {
+                    ClassData::Sierra {
+                        sierra_json: sierra,
+                        casm_json: casm,
+                    }
+                };
+
+                (data, "the given class".to_string())
+            }
+        };
+
+        if let ClassData::Sierra {
+            casm_json: None, ..
+        } = data
+        {
+            if !compile_casm {
+                return Err(Error::ArtifactsMissing(format!(
+                    "Casm artifacts not found for {}",
+                    name
+                )));
+            }
+        }
 
-        table
-            .set("class_hash", self.sierra_class_hash.clone())
-            .unwrap();
+        Ok(data)
     }
 }
 
-/// Defines a lua function that declares a contract.
+/// Defines a lua function that declares a contract from its artifacts.
+/// The artifacts are located from the contract name, unless the
+/// "sierra_path" option is given.
 ///
 /// # Arguments
 ///
 /// * `lua` - Lua VM instance.
-/// * `sierra_path` - Path to Sierra contract class file.
-/// * `casm_path` - Path to Casm (compiled) contract class file.
+/// * `contract_name` - Name of the contract.
 /// * `options` - Options for the declare transaction.
 pub fn lua_declare<'lua>(
     lua: &'lua Lua,
     contract_name: String,
     options: Table<'lua>,
 ) -> LuaResult<Table<'lua>> {
-    let (url_network, address, privkey, is_legacy) = lua::get_account(lua)?;
-    let artifacts_path: Option<String> = options.get("artifacts_path")?;
-    let is_recursive: bool = options.get("artifacts_recursively")?;
+    let sierra_path: Option<String> = options.get("sierra_path")?;
+
+    let source = match sierra_path {
+        Some(sierra_path) => ClassSource::Paths {
+            sierra_path,
+            casm_path: options.get("casm_path")?,
+        },
+        None => ClassSource::Name {
+            contract_name: contract_name.clone(),
+            artifacts_path: lua::get_artifacts_path(lua, &options)?,
+            is_recursive: options.get("artifacts_recursively")?,
+            scarb_build: lua::get_scarb_build(lua, &options)?,
+        },
+    };
+
+    declare(lua, &contract_name, source, options)
+}
+
+/// Defines a lua function that declares a class given as JSON strings.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `sierra_json` - Sierra class, or Cairo 0 compiled contract.
+/// * `casm_json` - Casm class, the Sierra class being compiled if `None`
+///   and the "compile_casm" option is set.
+/// * `options` - Options for the declare transaction.
+pub fn lua_declare_class<'lua>(
+    lua: &'lua Lua,
+    sierra_json: String,
+    casm_json: Option<String>,
+    options: Table<'lua>,
+) -> LuaResult<Table<'lua>> {
+    let source = ClassSource::Json {
+        sierra: sierra_json,
+        casm: casm_json,
+    };
+
+    declare(lua, "class", source, options)
+}
+
+/// Declares a class and returns the declare output as a Lua table.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `name` - Name of the declared class in the logs.
+/// * `source` - Source of the class to declare.
+/// * `options` - Options for the declare transaction.
+fn declare<'lua>(
+    lua: &'lua Lua,
+    name: &str,
+    source: ClassSource,
+    options: Table<'lua>,
+) -> LuaResult<Table<'lua>> {
     let skip_if_declared: bool = options.get("skip_if_declared")?;
-    let watch_interval = lua::get_watch_from_options(&options)?;
+    let compile_casm = lua::get_compile_casm(lua, &options)?;
+    let tx_options = lua::get_tx_options(lua, &options)?;
+    let label: Option<String> = options.get("label")?;
+
+    let mut out_log = String::from(&format!("> declare: {}\n", name));
+
+    if let Some(t) = state::lookup(lua, &label)? {
+        out_log.push_str(&format!(
+            "skipped: `{}` found in state\n",
+            label.unwrap_or_default()
+        ));
+        logger::write(lua, &out_log)?;
+
+        return Ok(t);
+    }
 
-    let mut out_log = String::from(&format!("> declare: {}\\n", contract_name));
+    let (url_network, address, signer, is_legacy) = lua::get_account(lua)?;
+    let chain_id = lua::get_chain_id(lua)?;
 
     let data = futures::executor::block_on(async move {
         RT.spawn(async move {
             let account =
-                match account::setup_account(&url_network, &address, &privkey, is_legacy).await {
+                match account::setup_account(&url_network, &address, signer, is_legacy, chain_id)
+                    .await
+                {
                     Ok(a) => a,
                     Err(e) => {
                         return LuaOutput {
                             data: None,
-                            error: format!("{:?}", e),
+                            error: Some(e),
                         }
                     }
                 };
 
-            let (sierra_path, casm_path) = match locate_artifacts(
-                &contract_name,
-                &artifacts_path.unwrap_or("./target/dev".to_string()),
-                is_recursive,
-            ) {
-                Ok((s, c)) => (s, c),
+            // Loading the class may run `scarb build` or the Sierra compiler,
+            // which must not block the runtime.
+            let class = match tokio::task::spawn_blocking(move || source.load(compile_casm))
+                .await
+                .map_err(|e| Error::Other(e.to_string()))
+                .and_then(|r| r)
+            {
+                Ok(c) => c,
                 Err(e) => {
                     return LuaOutput {
                         data: None,
-                        error: format!("{:?}", e),
+                        error: Some(e),
                     }
                 }
             };
 
-            match declare_tx(
-                account,
-                &sierra_path,
-                &casm_path,
-                skip_if_declared,
-                watch_interval,
-            )
-            .await
-            {
-                Ok((class_hash, decl_res)) => {
-                    // If the declaration is skipped due to already declared class,
-                    // we don't have any transaction hash.
-                    let transaction_hash =
-                        decl_res.map(|res| format!("0x{:064x}", res.transaction_hash));
-
-                    LuaOutput {
-                        data: Some(DeclareOutput {
-                            transaction_hash,
-                            sierra_class_hash: format!("0x{:064x}", class_hash),
-                        }),
-                        error: "".to_string(),
-                    }
+            let res = match &class {
+                ClassData::Sierra {
+                    sierra_json,
+                    casm_json,
+                } => {
+                    declare_tx(
+                        account,
+                        sierra_json,
+                        casm_json.as_deref(),
+                        skip_if_declared,
+                        &tx_options,
+                    )
+                    .await
+                }
+                ClassData::Legacy { json } => {
+                    declare_legacy_tx(account, json, skip_if_declared, &tx_options).await
                 }
+            };
+
+            match res {
+                // If the declaration is skipped due to already declared class,
+                // we don't have any transaction.
+                Ok((class_hash, transaction)) => LuaOutput {
+                    data: Some(DeclareOutput {
+                        transaction,
+                        class_hash: format!("0x{:064x}", class_hash),
+                    }),
+                    error: None,
+                },
                 Err(e) => LuaOutput {
                     data: None,
-                    error: format!("{:?}", e),
+                    error: Some(e),
                 },
             }
         })
@@ -121,23 +316,27 @@ pub fn lua_declare<'lua>(
         let t = lua.create_table()?;
         d.set_all(&t);
 
-        out_log.push_str(&format!(
-            "|     tx_hash      |  {}  |\\n",
-            d.transaction_hash.unwrap_or("N/A".to_string())
-        ));
-        out_log.push_str(&format!(
-            "|    class_hash    |  {}  |\\n",
-            d.sierra_class_hash
-        ));
+        if let Some(tx) = &d.transaction {
+            tx.log(&mut out_log);
+        } else {
+            out_log.push_str("|     tx_hash      |  N/A  |\n");
+        }
+        out_log.push_str(&format!("|    class_hash    |  {}  |\n", d.class_hash));
         logger::write(lua, &out_log)?;
 
+        state::record(lua, &label, "declare", &t)?;
+
+        if d.transaction.is_some() {
+            nonce::track(lua, d.transaction.as_ref())?;
+        }
+
         Ok(t)
     } else {
-        out_log.push_str(&format!("error: {}\\n", data.error));
+        nonce::track(lua, None)?;
+        let e = lua::output_error(data.error);
+        out_log.push_str(&format!("error: {}\n", e));
 
-        Err(LuaError::ExternalError(std::sync::Arc::new(
-            ErrorExtLua::new(&data.error),
-        )))
+        Err(e)
     }
 }
 
@@ -146,120 +345,285 @@ pub fn lua_declare<'lua>(
 /// # Arguments
 ///
 /// * `account` - The account used to sign and send the transaction.
-/// * `sierra_path` - Path to Sierra contract class file.
-/// * `casm_path` - Path to Casm (compiled) contract class file.
-/// * `watch_interval` - Watch interval for the transaction receipt.
+/// * `sierra_json` - Sierra contract class.
+/// * `casm_json` - Casm (compiled) contract class, the Sierra class being compiled if `None`.
+/// * `skip_if_declared` - If the declaration is skipped when the class is already declared.
+/// * `tx_options` - Options of the transaction.
 async fn declare_tx(
-    account: SingleOwnerAccount<AnyProvider, LocalWallet>,
-    sierra_path: &str,
-    casm_path: &str,
+    account: Arc<KiptAccount>,
+    sierra_json: &str,
+    casm_json: Option<&str>,
     skip_if_declared: bool,
-    watch_interval: Option<Duration>,
-) -> KiptResult<(FieldElement, Option<DeclareTransactionResult>)> {
-    // TODO: if the file is not found, the error returned by file::open is not giving the name.
-    // we might consider adding this somehow to have a more explicit error.
-    let casm_class = serde_json::from_reader::<_, CompiledClass>(std::fs::File::open(casm_path)?)?;
-
-    let sierra_class =
-        serde_json::from_reader::<_, SierraClass>(std::fs::File::open(sierra_path)?)?;
-
-    let sierra_class_hash = sierra_class.class_hash().unwrap();
-    let casm_class_hash = casm_class.class_hash().unwrap();
-
-    if skip_if_declared {
-        match account
-            .provider()
-            .get_class(BlockId::Tag(BlockTag::Pending), sierra_class_hash)
-            .await
-        {
-            Ok(_) => {
-                trace!(
-                    "Not declaring class as it's already declared. Class hash: 0x{:064x}",
-                    sierra_class_hash
-                );
-                return Ok((sierra_class_hash, None));
-            }
-            Err(ProviderError::StarknetError(StarknetErrorWithMessage {
-                code: MaybeUnknownErrorCode::Known(StarknetError::ClassHashNotFound),
-                ..
-            })) => trace!("Class not declared, declaring..."),
-            Err(err) => return Err(err.into()),
-        };
+    tx_options: &TxOptions,
+) -> KiptResult<(FieldElement, Option<TxOutcome>)> {
+    let sierra_class = serde_json::from_str::<SierraClass>(sierra_json)?;
+
+    let sierra_class_hash = sierra_class
+        .class_hash()
+        .map_err(|e| Error::Other(format!("Sierra class hash can't be computed: {}", e)))?;
+
+    let casm_class_hash = match casm_json {
+        Some(c) => {
+            let casm_class = serde_json::from_str::<CompiledClass>(c)?;
+            casm_class
+                .class_hash()
+                .map_err(|e| Error::Other(format!("Casm class hash can't be computed: {}", e)))?
+        }
+        None => {
+            trace!("Casm class not given, compiling Sierra");
+            casm::compiled_class_hash(sierra_json)?
+        }
+    };
+
+    if skip_if_declared && is_declared(&account, sierra_class_hash).await? {
+        return Ok((sierra_class_hash, None));
     }
 
     let declaration = account.declare(Arc::new(sierra_class.flatten()?), casm_class_hash);
-    let decl_res = declaration.send().await?;
 
-    if let Some(interval) = watch_interval {
-        transaction::poll_exec_succeeded(account.provider(), decl_res.transaction_hash, interval)
-            .await?;
+    Ok((
+        sierra_class_hash,
+        Some(send_declaration(&account, declaration, tx_options).await?),
+    ))
+}
+
+/// Sends a legacy (v1) transaction to declare a Cairo 0 contract.
+///
+/// # Arguments
+///
+/// * `account` - The account used to sign and send the transaction.
+/// * `json` - Cairo 0 compiled contract.
+/// * `skip_if_declared` - If the declaration is skipped when the class is already declared.
+/// * `tx_options` - Options of the transaction.
+async fn declare_legacy_tx(
+    account: Arc<KiptAccount>,
+    json: &str,
+    skip_if_declared: bool,
+    tx_options: &TxOptions,
+) -> KiptResult<(FieldElement, Option<TxOutcome>)> {
+    let contract_class = serde_json::from_str::<LegacyContractClass>(json).map_err(|e| {
+        Error::ArtifactsMissing(format!(
+            "Neither a Sierra class nor a Cairo 0 compiled contract: {}",
+            e
+        ))
+    })?;
+
+    let class_hash = contract_class
+        .class_hash()
+        .map_err(|e| Error::Other(format!("Legacy class hash can't be computed: {}", e)))?;
+
+    if skip_if_declared && is_declared(&account, class_hash).await? {
+        return Ok((class_hash, None));
+    }
+
+    let declaration = account.declare_legacy(Arc::new(contract_class));
+
+    Ok((
+        class_hash,
+        Some(send_declaration(&account, declaration, tx_options).await?),
+    ))
+}
+
+/// A declaration builder, for a Sierra class or a legacy (Cairo 0) class.
+#[async_trait]
+trait DeclarationTx: Exportable {
+    /// Sets the max fee of the declaration.
+    fn with_max_fee(self, max_fee: FieldElement) -> Self;
+
+    /// Sets the multiplier applied to the estimated fee.
+    fn with_fee_multiplier(self, multiplier: f64) -> Self;
+
+    /// Sends the declaration and returns its transaction hash.
+    async fn send_declaration(&self) -> KiptResult<FieldElement>;
+}
+
+#[async_trait]
+impl<A> DeclarationTx for Declaration<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    fn with_max_fee(self, max_fee: FieldElement) -> Self {
+        self.max_fee(max_fee)
+    }
+
+    fn with_fee_multiplier(self, multiplier: f64) -> Self {
+        self.fee_estimate_multiplier(multiplier)
+    }
+
+    async fn send_declaration(&self) -> KiptResult<FieldElement> {
+        Ok(self.send().await?.transaction_hash)
+    }
+}
+
+#[async_trait]
+impl<A> DeclarationTx for LegacyDeclaration<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    fn with_max_fee(self, max_fee: FieldElement) -> Self {
+        self.max_fee(max_fee)
     }
 
-    Ok((sierra_class_hash, Some(decl_res)))
+    fn with_fee_multiplier(self, multiplier: f64) -> Self {
+        self.fee_estimate_multiplier(multiplier)
+    }
+
+    async fn send_declaration(&self) -> KiptResult<FieldElement> {
+        Ok(self.send().await?.transaction_hash)
+    }
 }
 
-/// Locates the artifacts of a contract from it's name.
+/// Dry-runs, exports or sends a declaration, as set by the transaction options.
 ///
 /// # Arguments
 ///
-/// * `contract_name` - Name of the contract that will be tested as being the first part of the filename.
-/// * `artifacts_dir` - The directory where to search for the files.
-/// * `is_recursive` - If the search must be done recursively.
-fn locate_artifacts(
-    contract_name: &str,
-    artifacts_dir: &str,
-    is_recursive: bool,
-) -> KiptResult<(String, String)> {
-    let sierra_exts = [".contract_class.json", "sierra.json"];
-    let casm_exts = [".compiled_contract_class.json", "casm.json"];
-
-    let mut sierra_path: Option<String> = None;
-    let mut casm_path: Option<String> = None;
-
-    let dir = PathBuf::from(artifacts_dir).canonicalize()?;
-    trace!(
-        "Searching for contract {} artifacts in root path: {:?}",
-        contract_name,
-        dir
-    );
-
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let entry_path = entry.path();
-
-        if entry_path.is_dir() && is_recursive {
-            trace!("Recursive entry: {:?}", entry);
-            return locate_artifacts(contract_name, &entry_path.to_string_lossy(), is_recursive);
-        } else if let Some(file_name) = entry_path.file_name() {
-            trace!("Checking file for artifacts: {:?}", file_name);
-            let fname = file_name.to_string_lossy();
-            if !fname.starts_with(contract_name) || fname.split('.').next() != Some(contract_name) {
-                continue;
-            }
+/// * `account` - The account used to sign and send the transaction.
+/// * `declaration` - The declaration to send.
+/// * `tx_options` - Options of the transaction.
+async fn send_declaration<A, T>(
+    account: &A,
+    mut declaration: T,
+    tx_options: &TxOptions,
+) -> KiptResult<TxOutcome>
+where
+    A: ConnectedAccount + Sync,
+    T: DeclarationTx,
+{
+    let nonce = nonce::resolve(account, tx_options).await?;
+    if let Some(n) = nonce {
+        declaration = declaration.with_nonce(n);
+    }
 
-            if sierra_exts.iter().any(|ext| fname.ends_with(ext)) {
-                trace!("Sierra artifact found: {}", fname);
-                sierra_path = Some(entry_path.canonicalize()?.to_string_lossy().to_string());
-            } else if casm_exts.iter().any(|ext| fname.ends_with(ext)) {
-                trace!("Casm artifact found: {}", fname);
-                casm_path = Some(entry_path.canonicalize()?.to_string_lossy().to_string());
-            }
-        }
+    if let Some(max_fee) = tx_options.max_fee {
+        declaration = declaration.with_max_fee(max_fee);
     }
 
-    match (sierra_path, casm_path) {
-        (Some(s), Some(c)) => Ok((s.to_string(), c.to_string())),
-        (None, _) => {
-            // TODO: add a detail of file name that were looked for + a detail of directories?
-            // Perhaps only with `trace!` level.
-            Err(Error::ArtifactsMissing(format!(
-                "Sierra artifacts not found for contract {}",
-                contract_name
-            )))
+    if let Some(multiplier) = tx_options.fee_multiplier {
+        declaration = declaration.with_fee_multiplier(multiplier);
+    }
+
+    if tx_options.dry_run {
+        let nonce = nonce::or_fetch(account, nonce).await?;
+        let (transaction_hash, fee) = fee::dry_run(declaration, nonce, tx_options).await?;
+
+        return Ok(TxOutcome {
+            transaction_hash,
+            nonce: Some(nonce),
+            fee: Some(fee),
+            exported: false,
+        });
+    }
+
+    if let Some(path) = &tx_options.export {
+        let (transaction_hash, nonce) =
+            export::export(account, declaration, nonce, tx_options, path).await?;
+
+        return Ok(TxOutcome {
+            transaction_hash,
+            nonce: Some(nonce),
+            fee: None,
+            exported: true,
+        });
+    }
+
+    let transaction_hash = declaration.send_declaration().await?;
+
+    if let Some(interval) = tx_options.watch_interval {
+        transaction::poll_exec_succeeded(account.provider(), transaction_hash, interval).await?;
+    }
+
+    Ok(TxOutcome {
+        transaction_hash,
+        nonce,
+        fee: None,
+        exported: false,
+    })
+}
+
+/// Reads an artifact file, the error giving the path of the file.
+///
+/// # Arguments
+///
+/// * `path` - Path of the artifact.
+fn read_artifact(path: &str) -> KiptResult<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| Error::ArtifactsMissing(format!("{} can't be read: {}", path, e)))
+}
+
+/// Returns true if the JSON is a Cairo 0 compiled contract, which has
+/// a `program` instead of the `sierra_program` of the Sierra classes.
+///
+/// # Arguments
+///
+/// * `json` - The class to check.
+fn is_legacy_class(json: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(json)
+        .is_ok_and(|v| v.get("program").is_some() && v.get("sierra_program").is_none())
+}
+
+/// Returns true if the class is already declared.
+///
+/// # Arguments
+///
+/// * `account` - The account whose provider is used to fetch the class.
+/// * `class_hash` - Hash of the class.
+pub async fn is_declared<A>(account: &A, class_hash: FieldElement) -> KiptResult<bool>
+where
+    A: ConnectedAccount + Sync,
+{
+    match account
+        .provider()
+        .get_class(BlockId::Tag(BlockTag::Pending), class_hash)
+        .await
+    {
+        Ok(_) => {
+            trace!(
+                "Not declaring class as it's already declared. Class hash: 0x{:064x}",
+                class_hash
+            );
+            Ok(true)
+        }
+        Err(ProviderError::StarknetError(StarknetErrorWithMessage {
+            code: MaybeUnknownErrorCode::Known(StarknetError::ClassHashNotFound),
+            ..
+        })) => {
+            trace!("Class not declared, declaring...");
+            Ok(false)
         }
-        (_, None) => Err(Error::ArtifactsMissing(format!(
-            "Casm artifacts not found for contract {}",
-            contract_name
-        ))),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sierra_class_is_not_legacy() {
+        let sierra = std::fs::read_to_string("contracts/artifacts/c1.sierra.json").unwrap();
+
+        assert!(!is_legacy_class(&sierra));
+    }
+
+    #[test]
+    fn cairo_0_class_is_legacy() {
+        let legacy = r#"{
+            "abi": [],
+            "entry_points_by_type": { "CONSTRUCTOR": [], "EXTERNAL": [], "L1_HANDLER": [] },
+            "program": { "data": [], "prime": "0x800000000000011000000000000000000000000000000000000000000000001" }
+        }"#;
+
+        assert!(is_legacy_class(legacy));
+    }
+
+    #[test]
+    fn invalid_class_is_not_legacy() {
+        assert!(!is_legacy_class("not a class"));
+        assert!(!is_legacy_class("{}"));
+        assert!(!is_legacy_class(
+            r#"{ "program": {}, "sierra_program": [] }"#
+        ));
     }
 }
@@ -1,27 +1,32 @@
-use mlua::{Error as LuaError, Lua, Result as LuaResult, Table};
+use mlua::{Lua, Result as LuaResult, Table};
 use starknet::{
-    accounts::{ConnectedAccount, SingleOwnerAccount},
+    accounts::{ConnectedAccount, Execution},
     contract::ContractFactory,
-    core::types::{FieldElement, InvokeTransactionResult},
-    providers::AnyProvider,
-    signers::{LocalWallet, SigningKey},
+    core::types::FieldElement,
+    signers::SigningKey,
 };
 use std::sync::Arc;
-use std::time::Duration;
+use tracing::trace;
 
-use crate::error::{ErrorExtLua, KiptResult};
+use crate::account::KiptAccount;
+use crate::error::KiptResult;
 use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
-use crate::{account, logger, transaction};
+use crate::transaction::{TxOptions, TxOutcome};
+use crate::{account, declare, export, fee, logger, nonce, state, transaction};
 
 /// Deploy output.
 struct DeployOutput {
-    pub transaction_hash: String,
+    /// `None` for a dry-run if the class is not declared yet.
+    pub transaction: Option<TxOutcome>,
     pub deployed_address: String,
 }
 
 impl LuaTableSetable for DeployOutput {
     fn set_all(&self, table: &Table) {
-        table.set("tx_hash", self.transaction_hash.clone()).unwrap();
+        match &self.transaction {
+            Some(tx) => tx.set_all(table),
+            None => table.set("dry_run", true).unwrap(),
+        }
 
         table
             .set("deployed_address", self.deployed_address.clone())
@@ -35,45 +40,63 @@ impl LuaTableSetable for DeployOutput {
 ///
 /// * `lua` - Lua VM instance.
 /// * `sierra_class_hash` - Contract class hash.
-/// * `args` - Constructor arguments.
+/// * `args` - Constructor arguments, encoded using the contract ABI
+///   if the "abi" option is given.
 /// * `options` - Options for the deploy transaction.
 pub fn lua_deploy<'lua>(
     lua: &'lua Lua,
     sierra_class_hash: String,
-    args: Vec<String>,
+    args: Table<'lua>,
     options: Table<'lua>,
 ) -> LuaResult<Table<'lua>> {
-    let (url_network, address, privkey, is_legacy) = lua::get_account(lua)?;
+    let abi = lua::get_abi_from_options(lua, &options)?;
+    let args = lua::encode_args(abi.as_ref(), |abi| Ok(abi.constructor_inputs()), &args)?;
 
-    let watch_interval = lua::get_watch_from_options(&options)?;
+    let tx_options = lua::get_tx_options(lua, &options)?;
+    let label: Option<String> = options.get("label")?;
     let salt: Option<String> = options.get("salt")?;
 
-    let mut out_log = String::from(&format!("> deploy: {}\\n", sierra_class_hash));
+    let mut out_log = String::from(&format!("> deploy: {}\n", sierra_class_hash));
+
+    if let Some(t) = state::lookup(lua, &label)? {
+        out_log.push_str(&format!(
+            "skipped: `{}` found in state\n",
+            label.unwrap_or_default()
+        ));
+        logger::write(lua, &out_log)?;
+
+        return Ok(t);
+    }
+
+    let (url_network, address, signer, is_legacy) = lua::get_account(lua)?;
+    let chain_id = lua::get_chain_id(lua)?;
 
     let data = futures::executor::block_on(async move {
         RT.spawn(async move {
             let account =
-                match account::setup_account(&url_network, &address, &privkey, is_legacy).await {
+                match account::setup_account(&url_network, &address, signer, is_legacy, chain_id)
+                    .await
+                {
                     Ok(a) => a,
                     Err(e) => {
                         return LuaOutput {
                             data: None,
-                            error: format!("{:?}", e),
+                            error: Some(e),
                         }
                     }
                 };
 
-            match deploy_tx(account, &sierra_class_hash, &args, salt, watch_interval).await {
-                Ok((deployed_address, depl_res)) => LuaOutput {
+            match deploy_tx(account, &sierra_class_hash, &args, salt, &tx_options).await {
+                Ok((deployed_address, transaction)) => LuaOutput {
                     data: Some(DeployOutput {
-                        transaction_hash: format!("0x{:064x}", depl_res.transaction_hash),
+                        transaction,
                         deployed_address: format!("0x{:064x}", deployed_address),
                     }),
-                    error: "".to_string(),
+                    error: None,
                 },
                 Err(e) => LuaOutput {
                     data: None,
-                    error: format!("{:?}", e),
+                    error: Some(e),
                 },
             }
         })
@@ -86,23 +109,28 @@ pub fn lua_deploy<'lua>(
 
         d.set_all(&t);
 
+        match &d.transaction {
+            Some(tx) => tx.log(&mut out_log),
+            None => {
+                out_log.push_str("|     dry-run      |  class not declared, fee not estimated  |\n")
+            }
+        }
         out_log.push_str(&format!(
-            "|     tx_hash      |  {}  |\\n",
-            d.transaction_hash
-        ));
-        out_log.push_str(&format!(
-            "| deployed address |  {}  |\\n",
+            "| deployed address |  {}  |\n",
             d.deployed_address
         ));
         logger::write(lua, &out_log)?;
 
+        state::record(lua, &label, "deploy", &t)?;
+        nonce::track(lua, d.transaction.as_ref())?;
+
         Ok(t)
     } else {
-        out_log.push_str(&format!("error: {}\\n", data.error));
+        nonce::track(lua, None)?;
+        let e = lua::output_error(data.error);
+        out_log.push_str(&format!("error: {}\n", e));
 
-        Err(LuaError::ExternalError(std::sync::Arc::new(
-            ErrorExtLua::new(&data.error),
-        )))
+        Err(e)
     }
 }
 
@@ -115,14 +143,14 @@ pub fn lua_deploy<'lua>(
 /// * `args` - Constructor arguments.
 /// * `salt` - Optional salt for contract address computation. A random value
 ///   is used if `None` is provided.
-/// * `watch_interval` - Watch interval for the transaction receipt.
+/// * `tx_options` - Options of the transaction.
 async fn deploy_tx(
-    account: SingleOwnerAccount<AnyProvider, LocalWallet>,
+    account: Arc<KiptAccount>,
     sierra_class_hash: &str,
     args: &[String],
     salt: Option<String>,
-    watch_interval: Option<Duration>,
-) -> KiptResult<(FieldElement, InvokeTransactionResult)> {
+    tx_options: &TxOptions,
+) -> KiptResult<(FieldElement, Option<TxOutcome>)> {
     let class_hash = FieldElement::from_hex_be(sierra_class_hash)?;
 
     let mut ctor_args: Vec<FieldElement> = vec![];
@@ -136,19 +164,92 @@ async fn deploy_tx(
         SigningKey::from_random().secret_scalar()
     };
 
-    let account = Arc::new(account);
     let factory = ContractFactory::new(class_hash, Arc::clone(&account));
 
     let is_unique = false;
-    let contract_deployment = factory.deploy(ctor_args, salt, is_unique);
+    let mut contract_deployment = factory.deploy(ctor_args, salt, is_unique);
+
+    let nonce = nonce::resolve(&account, tx_options).await?;
+    if let Some(n) = nonce {
+        contract_deployment = contract_deployment.nonce(n);
+    }
+
+    if let Some(max_fee) = tx_options.max_fee {
+        contract_deployment = contract_deployment.max_fee(max_fee);
+    }
+
+    if let Some(multiplier) = tx_options.fee_multiplier {
+        contract_deployment = contract_deployment.fee_estimate_multiplier(multiplier);
+    }
+
     let deployed_address = contract_deployment.deployed_address();
 
+    if tx_options.dry_run {
+        let nonce = nonce::or_fetch(&account, nonce).await?;
+
+        return match fee::dry_run(Execution::from(&contract_deployment), nonce, tx_options).await {
+            Ok((transaction_hash, fee)) => Ok((
+                deployed_address,
+                Some(TxOutcome {
+                    transaction_hash,
+                    nonce: Some(nonce),
+                    fee: Some(fee),
+                    exported: false,
+                }),
+            )),
+            Err(e) => {
+                // The class may be declared earlier in the same dry-run, only
+                // the address of the contract can be predicted in this case.
+                match declare::is_declared(&account, class_hash).await {
+                    Ok(false) => {
+                        trace!("Class not declared, fee not estimated: {}", e);
+                        Ok((deployed_address, None))
+                    }
+                    Ok(true) => Err(e),
+                    Err(de) => {
+                        trace!("Declaration of the class can't be checked: {}", de);
+                        Err(e)
+                    }
+                }
+            }
+        };
+    }
+
+    if let Some(path) = &tx_options.export {
+        let (transaction_hash, nonce) = export::export(
+            &account,
+            Execution::from(&contract_deployment),
+            nonce,
+            tx_options,
+            path,
+        )
+        .await?;
+
+        return Ok((
+            deployed_address,
+            Some(TxOutcome {
+                transaction_hash,
+                nonce: Some(nonce),
+                fee: None,
+                exported: true,
+            }),
+        ));
+    }
+
     let depl_res = contract_deployment.send().await?;
 
-    if let Some(interval) = watch_interval {
+    if let Some(interval) = tx_options.watch_interval {
         transaction::poll_exec_succeeded(account.provider(), depl_res.transaction_hash, interval)
             .await?;
     }
 
-    Ok((deployed_address, depl_res))
+    Ok((
+        deployed_address,
+        Some(TxOutcome {
+            transaction_hash: depl_res.transaction_hash,
+            nonce,
+            fee: None,
+            exported: false,
+        }),
+    ))
 }
@@ -0,0 +1,807 @@
+//! Deployment of new accounts with a `DEPLOY_ACCOUNT` transaction,
+//! for the OpenZeppelin, Argent and Braavos account classes.
+use async_trait::async_trait;
+use mlua::{Lua, Result as LuaResult, Table};
+use starknet::{
+    accounts::{
+        AccountFactory, ArgentAccountFactory, OpenZeppelinAccountFactory,
+        PreparedAccountDeployment, RawAccountDeployment,
+    },
+    core::{
+        crypto::compute_hash_on_elements,
+        types::{BlockId, BlockTag, FieldElement},
+        utils::{get_contract_address, get_selector_from_name},
+    },
+    providers::AnyProvider,
+    signers::{local_wallet::SignError, LocalWallet, Signer, SigningKey},
+};
+
+use crate::error::{Error, KiptResult};
+use crate::fee::{self, FeeOutput};
+use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
+use crate::signer::KiptSigner;
+use crate::transaction::{TxOptions, TxOutcome};
+use crate::{account, logger, state, transaction};
+
+/// Keypair output.
+struct KeypairOutput {
+    pub private_key: FieldElement,
+    pub public_key: FieldElement,
+}
+
+impl LuaTableSetable for KeypairOutput {
+    fn set_all(&self, table: &Table) {
+        table
+            .set("private_key", format!("0x{:064x}", self.private_key))
+            .unwrap();
+        table
+            .set("public_key", format!("0x{:064x}", self.public_key))
+            .unwrap();
+    }
+}
+
+/// Deploy account output.
+struct DeployAccountOutput {
+    pub transaction: TxOutcome,
+    pub address: String,
+}
+
+impl LuaTableSetable for DeployAccountOutput {
+    fn set_all(&self, table: &Table) {
+        self.transaction.set_all(table);
+
+        table.set("address", self.address.clone()).unwrap();
+    }
+}
+
+/// Account classes supported for the deployment.
+#[derive(Debug, Clone)]
+enum AccountClass {
+    OpenZeppelin {
+        class_hash: FieldElement,
+    },
+    Argent {
+        class_hash: FieldElement,
+        guardian: FieldElement,
+    },
+    /// Braavos accounts are deployed as a proxy, initialized with
+    /// a mock implementation and upgraded to the actual implementation
+    /// given in the signature.
+    Braavos {
+        class_hash: FieldElement,
+        mock_class_hash: FieldElement,
+        implementation: FieldElement,
+    },
+}
+
+impl AccountClass {
+    /// Builds the account class from its kind and the options.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of account: "oz", "argent" or "braavos".
+    /// * `options` - Lua table with the class hashes of the account.
+    fn from_options(kind: &str, options: &Table) -> LuaResult<Self> {
+        let class_hash = felt_from_options(options, "class_hash")?
+            .ok_or_else(|| Error::Other(format!("class_hash is required for {} accounts", kind)))?;
+
+        match kind {
+            "oz" => Ok(Self::OpenZeppelin { class_hash }),
+            "argent" => Ok(Self::Argent {
+                class_hash,
+                guardian: felt_from_options(options, "guardian")?.unwrap_or(FieldElement::ZERO),
+            }),
+            "braavos" => {
+                let mock_class_hash = felt_from_options(options, "mock_class_hash")?;
+                let implementation = felt_from_options(options, "implementation")?;
+
+                match (mock_class_hash, implementation) {
+                    (Some(mock_class_hash), Some(implementation)) => Ok(Self::Braavos {
+                        class_hash,
+                        mock_class_hash,
+                        implementation,
+                    }),
+                    _ => Err(Error::Other(
+                        "mock_class_hash and implementation are required for braavos accounts"
+                            .to_string(),
+                    )
+                    .into()),
+                }
+            }
+            _ => Err(Error::Other(format!(
+                "Invalid account kind: {} (expected oz, argent or braavos)",
+                kind
+            ))
+            .into()),
+        }
+    }
+
+    /// Returns the class hash of the deployed contract.
+    fn class_hash(&self) -> FieldElement {
+        match self {
+            Self::OpenZeppelin { class_hash }
+            | Self::Argent { class_hash, .. }
+            | Self::Braavos { class_hash, .. } => *class_hash,
+        }
+    }
+
+    /// Returns the constructor calldata of the account.
+    ///
+    /// # Arguments
+    ///
+    /// * `public_key` - The public key of the account signer.
+    fn calldata(&self, public_key: FieldElement) -> Vec<FieldElement> {
+        match self {
+            Self::OpenZeppelin { .. } => vec![public_key],
+            Self::Argent { guardian, .. } => vec![public_key, *guardian],
+            Self::Braavos {
+                mock_class_hash, ..
+            } => vec![
+                *mock_class_hash,
+                get_selector_from_name("initializer").expect("Selector is ascii"),
+                FieldElement::ONE,
+                public_key,
+            ],
+        }
+    }
+
+    /// Returns the address of the account once deployed (counterfactual address).
+    ///
+    /// # Arguments
+    ///
+    /// * `public_key` - The public key of the account signer.
+    /// * `salt` - The salt of the deployment.
+    fn address(&self, public_key: FieldElement, salt: FieldElement) -> FieldElement {
+        get_contract_address(
+            salt,
+            self.class_hash(),
+            &self.calldata(public_key),
+            FieldElement::ZERO,
+        )
+    }
+}
+
+/// Factory for Braavos accounts, which are not supported by starknet-rs.
+struct BraavosAccountFactory {
+    class: AccountClass,
+    implementation: FieldElement,
+    chain_id: FieldElement,
+    public_key: FieldElement,
+    signer: KiptSigner,
+    provider: AnyProvider,
+}
+
+#[async_trait]
+impl AccountFactory for BraavosAccountFactory {
+    type Provider = AnyProvider;
+    type SignError = SignError;
+
+    fn class_hash(&self) -> FieldElement {
+        self.class.class_hash()
+    }
+
+    fn calldata(&self) -> Vec<FieldElement> {
+        self.class.calldata(self.public_key)
+    }
+
+    fn chain_id(&self) -> FieldElement {
+        self.chain_id
+    }
+
+    fn provider(&self) -> &Self::Provider {
+        &self.provider
+    }
+
+    fn block_id(&self) -> BlockId {
+        BlockId::Tag(BlockTag::Pending)
+    }
+
+    async fn sign_deployment(
+        &self,
+        deployment: &RawAccountDeployment,
+    ) -> Result<Vec<FieldElement>, Self::SignError> {
+        let tx_hash =
+            PreparedAccountDeployment::from_raw(deployment.clone(), self).transaction_hash();
+
+        self.sign_with_aux_data(tx_hash).await
+    }
+}
+
+impl BraavosAccountFactory {
+    /// Signs the hash of a deployment with the auxiliary data expected
+    /// by the Braavos proxy, and returns the full signature.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The hash of the deployment transaction.
+    async fn sign_with_aux_data(
+        &self,
+        tx_hash: FieldElement,
+    ) -> Result<Vec<FieldElement>, SignError> {
+        // The actual implementation, followed by the (unused) hardware signer fields,
+        // which are also part of the signed hash.
+        let mut aux_data = vec![self.implementation];
+        aux_data.extend_from_slice(&[FieldElement::ZERO; 7]);
+
+        let mut hashed = vec![tx_hash];
+        hashed.extend_from_slice(&aux_data);
+        let signature = self
+            .signer
+            .sign_hash(&compute_hash_on_elements(&hashed))
+            .await?;
+
+        let mut full_signature = vec![signature.r, signature.s];
+        full_signature.extend_from_slice(&aux_data);
+
+        Ok(full_signature)
+    }
+}
+
+/// An account factory for any of the supported account classes.
+enum KiptAccountFactory {
+    OpenZeppelin(OpenZeppelinAccountFactory<KiptSigner, AnyProvider>),
+    Argent(ArgentAccountFactory<KiptSigner, AnyProvider>),
+    Braavos(BraavosAccountFactory),
+}
+
+impl KiptAccountFactory {
+    /// Builds the factory for the given account class.
+    ///
+    /// # Arguments
+    ///
+    /// * `url_network` - The RPC URL or network name of the provider.
+    /// * `chain_id` - Chain id to use instead of fetching it from the provider.
+    /// * `class` - The account class to deploy.
+    /// * `private_key` - The private key of the account signer.
+    /// * `provider` - The provider used to send the transaction.
+    async fn new(
+        url_network: &str,
+        chain_id: Option<FieldElement>,
+        class: AccountClass,
+        private_key: FieldElement,
+        provider: AnyProvider,
+    ) -> KiptResult<Self> {
+        let chain_id = account::resolve_chain_id(url_network, &provider, chain_id).await?;
+        let signing_key = SigningKey::from_secret_scalar(private_key);
+        let public_key = signing_key.verifying_key().scalar();
+        let signer = KiptSigner::PrivateKey(LocalWallet::from(signing_key));
+
+        let mut factory = match class {
+            AccountClass::OpenZeppelin { class_hash } => Self::OpenZeppelin(
+                OpenZeppelinAccountFactory::new(class_hash, chain_id, signer, provider)
+                    .await
+                    .unwrap_or_else(|e| match e {}),
+            ),
+            AccountClass::Argent {
+                class_hash,
+                guardian,
+            } => Self::Argent(
+                ArgentAccountFactory::new(class_hash, chain_id, guardian, signer, provider)
+                    .await
+                    .unwrap_or_else(|e| match e {}),
+            ),
+            AccountClass::Braavos { implementation, .. } => Self::Braavos(BraavosAccountFactory {
+                class,
+                implementation,
+                chain_id,
+                public_key,
+                signer,
+                provider,
+            }),
+        };
+
+        // The account may have been funded in the pending block.
+        let block_id = BlockId::Tag(BlockTag::Pending);
+        match &mut factory {
+            Self::OpenZeppelin(f) => {
+                f.set_block_id(block_id);
+            }
+            Self::Argent(f) => {
+                f.set_block_id(block_id);
+            }
+            Self::Braavos(_) => {}
+        };
+
+        Ok(factory)
+    }
+}
+
+#[async_trait]
+impl AccountFactory for KiptAccountFactory {
+    type Provider = AnyProvider;
+    type SignError = SignError;
+
+    fn class_hash(&self) -> FieldElement {
+        match self {
+            Self::OpenZeppelin(f) => f.class_hash(),
+            Self::Argent(f) => f.class_hash(),
+            Self::Braavos(f) => f.class_hash(),
+        }
+    }
+
+    fn calldata(&self) -> Vec<FieldElement> {
+        match self {
+            Self::OpenZeppelin(f) => f.calldata(),
+            Self::Argent(f) => f.calldata(),
+            Self::Braavos(f) => f.calldata(),
+        }
+    }
+
+    fn chain_id(&self) -> FieldElement {
+        match self {
+            Self::OpenZeppelin(f) => f.chain_id(),
+            Self::Argent(f) => f.chain_id(),
+            Self::Braavos(f) => f.chain_id(),
+        }
+    }
+
+    fn provider(&self) -> &Self::Provider {
+        match self {
+            Self::OpenZeppelin(f) => f.provider(),
+            Self::Argent(f) => f.provider(),
+            Self::Braavos(f) => f.provider(),
+        }
+    }
+
+    fn block_id(&self) -> BlockId {
+        match self {
+            Self::OpenZeppelin(f) => f.block_id(),
+            Self::Argent(f) => f.block_id(),
+            Self::Braavos(f) => f.block_id(),
+        }
+    }
+
+    async fn sign_deployment(
+        &self,
+        deployment: &RawAccountDeployment,
+    ) -> Result<Vec<FieldElement>, Self::SignError> {
+        match self {
+            Self::OpenZeppelin(f) => f.sign_deployment(deployment).await,
+            Self::Argent(f) => f.sign_deployment(deployment).await,
+            Self::Braavos(f) => f.sign_deployment(deployment).await,
+        }
+    }
+}
+
+/// Defines a lua function that generates a new random keypair.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn lua_new_keypair(lua: &Lua) -> LuaResult<Table<'_>> {
+    let signing_key = SigningKey::from_random();
+
+    let t = lua.create_table()?;
+    KeypairOutput {
+        private_key: signing_key.secret_scalar(),
+        public_key: signing_key.verifying_key().scalar(),
+    }
+    .set_all(&t);
+
+    Ok(t)
+}
+
+/// Defines a lua function that computes the address of an account
+/// before it's deployed (counterfactual address).
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `kind` - The kind of account: "oz", "argent" or "braavos".
+/// * `public_key` - The public key of the account signer.
+/// * `options` - Options for the account deployment.
+pub fn lua_account_address(
+    _lua: &Lua,
+    kind: String,
+    public_key: String,
+    options: Table,
+) -> LuaResult<String> {
+    let class = AccountClass::from_options(&kind, &options)?;
+    let public_key = FieldElement::from_hex_be(&public_key).map_err(Error::from)?;
+    let salt = felt_from_options(&options, "salt")?.unwrap_or(public_key);
+
+    Ok(format!("0x{:064x}", class.address(public_key, salt)))
+}
+
+/// Defines a lua function that estimates the fee of an account deployment.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `kind` - The kind of account: "oz", "argent" or "braavos".
+/// * `private_key` - The private key of the account signer.
+/// * `options` - Options for the account deployment.
+pub fn lua_estimate_deploy_account_fee<'lua>(
+    lua: &'lua Lua,
+    kind: String,
+    private_key: String,
+    options: Table<'lua>,
+) -> LuaResult<Table<'lua>> {
+    let url_network = lua::get_provider(lua)?;
+    let chain_id = lua::get_chain_id(lua)?;
+    let class = AccountClass::from_options(&kind, &options)?;
+    let private_key = FieldElement::from_hex_be(&private_key).map_err(Error::from)?;
+    let salt = felt_from_options(&options, "salt")?;
+
+    let mut out_log = String::from(&format!("> estimate_deploy_account_fee: {}\n", kind));
+
+    let data = futures::executor::block_on(async move {
+        RT.spawn(async move {
+            match estimate_deploy_account_fee(&url_network, chain_id, class, private_key, salt)
+                .await
+            {
+                Ok(fee) => LuaOutput {
+                    data: Some(fee),
+                    error: None,
+                },
+                Err(e) => LuaOutput {
+                    data: None,
+                    error: Some(e),
+                },
+            }
+        })
+        .await
+        .unwrap()
+    });
+
+    if let Some(d) = data.data {
+        let t = lua.create_table()?;
+        d.set_all(&t);
+
+        out_log.push_str(&format!("|   overall_fee    |  {}  |\n", d.overall_fee));
+        logger::write(lua, &out_log)?;
+
+        Ok(t)
+    } else {
+        Err(lua::output_error(data.error))
+    }
+}
+
+/// Defines a lua function that deploys an account.
+/// The account address must be funded before the deployment.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `kind` - The kind of account: "oz", "argent" or "braavos".
+/// * `private_key` - The private key of the account signer.
+/// * `options` - Options for the account deployment.
+pub fn lua_deploy_account<'lua>(
+    lua: &'lua Lua,
+    kind: String,
+    private_key: String,
+    options: Table<'lua>,
+) -> LuaResult<Table<'lua>> {
+    let url_network = lua::get_provider(lua)?;
+    let chain_id = lua::get_chain_id(lua)?;
+    let class = AccountClass::from_options(&kind, &options)?;
+    let private_key = FieldElement::from_hex_be(&private_key).map_err(Error::from)?;
+    let salt = felt_from_options(&options, "salt")?;
+
+    // The nonce tracking only applies to the current account, not the deployed one.
+    let tx_options = lua::get_untracked_tx_options(lua, &options)?;
+    let label: Option<String> = options.get("label")?;
+
+    let mut out_log = String::from(&format!("> deploy_account: {}\n", kind));
+
+    if let Some(t) = state::lookup(lua, &label)? {
+        out_log.push_str(&format!(
+            "skipped: `{}` found in state\n",
+            label.unwrap_or_default()
+        ));
+        logger::write(lua, &out_log)?;
+
+        return Ok(t);
+    }
+
+    let data = futures::executor::block_on(async move {
+        RT.spawn(async move {
+            match deploy_account_tx(
+                &url_network,
+                chain_id,
+                class,
+                private_key,
+                salt,
+                &tx_options,
+            )
+            .await
+            {
+                Ok((address, transaction)) => LuaOutput {
+                    data: Some(DeployAccountOutput {
+                        transaction,
+                        address: format!("0x{:064x}", address),
+                    }),
+                    error: None,
+                },
+                Err(e) => LuaOutput {
+                    data: None,
+                    error: Some(e),
+                },
+            }
+        })
+        .await
+        .unwrap()
+    });
+
+    if let Some(d) = data.data {
+        let t = lua.create_table()?;
+        d.set_all(&t);
+
+        d.transaction.log(&mut out_log);
+        out_log.push_str(&format!("|     address      |  {}  |\n", d.address));
+        logger::write(lua, &out_log)?;
+
+        state::record(lua, &label, "deploy_account", &t)?;
+
+        Ok(t)
+    } else {
+        Err(lua::output_error(data.error))
+    }
+}
+
+/// Estimates the fee of an account deployment.
+///
+/// # Arguments
+///
+/// * `url_network` - The RPC URL or network name.
+/// * `chain_id` - Chain id to use instead of fetching it from the provider.
+/// * `class` - The account class to deploy.
+/// * `private_key` - The private key of the account signer.
+/// * `salt` - The salt of the deployment, the public key if `None`.
+async fn estimate_deploy_account_fee(
+    url_network: &str,
+    chain_id: Option<FieldElement>,
+    class: AccountClass,
+    private_key: FieldElement,
+    salt: Option<FieldElement>,
+) -> KiptResult<FeeOutput> {
+    let provider = account::setup_provider(url_network).await?;
+    let factory =
+        KiptAccountFactory::new(url_network, chain_id, class, private_key, provider).await?;
+
+    let salt = salt.unwrap_or_else(|| {
+        SigningKey::from_secret_scalar(private_key)
+            .verifying_key()
+            .scalar()
+    });
+    let fee = factory.deploy(salt).estimate_fee().await?;
+
+    Ok(FeeOutput::from((fee, false)))
+}
+
+/// Sends a transaction to deploy an account.
+///
+/// # Arguments
+///
+/// * `url_network` - The RPC URL or network name.
+/// * `chain_id` - Chain id to use instead of fetching it from the provider.
+/// * `class` - The account class to deploy.
+/// * `private_key` - The private key of the account signer.
+/// * `salt` - The salt of the deployment, the public key if `None`.
+/// * `tx_options` - Options of the transaction.
+async fn deploy_account_tx(
+    url_network: &str,
+    chain_id: Option<FieldElement>,
+    class: AccountClass,
+    private_key: FieldElement,
+    salt: Option<FieldElement>,
+    tx_options: &TxOptions,
+) -> KiptResult<(FieldElement, TxOutcome)> {
+    let provider = account::setup_provider(url_network).await?;
+    let factory =
+        KiptAccountFactory::new(url_network, chain_id, class, private_key, provider).await?;
+
+    let salt = salt.unwrap_or_else(|| {
+        SigningKey::from_secret_scalar(private_key)
+            .verifying_key()
+            .scalar()
+    });
+    let mut deployment = factory.deploy(salt);
+    let address = deployment.address();
+
+    if let Some(n) = tx_options.nonce {
+        deployment = deployment.nonce(n);
+    }
+
+    if let Some(max_fee) = tx_options.max_fee {
+        deployment = deployment.max_fee(max_fee);
+    }
+
+    if let Some(multiplier) = tx_options.fee_multiplier {
+        deployment = deployment.fee_estimate_multiplier(multiplier);
+    }
+
+    if tx_options.dry_run {
+        let nonce = match tx_options.nonce {
+            Some(n) => n,
+            None => deployment.fetch_nonce().await?,
+        };
+        let (transaction_hash, fee) = fee::dry_run(deployment, nonce, tx_options).await?;
+
+        return Ok((
+            address,
+            TxOutcome {
+                transaction_hash,
+                nonce: Some(nonce),
+                fee: Some(fee),
+                exported: false,
+            },
+        ));
+    }
+
+    // The factory of starknet-rs doesn't expose the signed deployment request,
+    // the account deployment is never sent in export mode.
+    if tx_options.export.is_some() {
+        return Err(Error::Other(
+            "Account deployments can't be exported, unset EXPORT_TX to deploy the account"
+                .to_string(),
+        ));
+    }
+
+    let depl_res = deployment.send().await?;
+
+    if let Some(interval) = tx_options.watch_interval {
+        transaction::poll_exec_succeeded(factory.provider(), depl_res.transaction_hash, interval)
+            .await?;
+    }
+
+    Ok((
+        address,
+        TxOutcome {
+            transaction_hash: depl_res.transaction_hash,
+            nonce: tx_options.nonce,
+            fee: None,
+            exported: false,
+        },
+    ))
+}
+
+/// Retrieves an optional `FieldElement` from the options.
+///
+/// # Arguments
+///
+/// * `options` - Lua table with the options.
+/// * `key` - The key of the option.
+fn felt_from_options(options: &Table, key: &str) -> LuaResult<Option<FieldElement>> {
+    let value: Option<String> = options.get(key)?;
+
+    match value {
+        Some(v) => Ok(Some(FieldElement::from_hex_be(&v).map_err(Error::from)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::{
+        accounts::AccountDeployment,
+        core::crypto::{ecdsa_verify, Signature},
+        providers::{jsonrpc::HttpTransport, JsonRpcClient},
+    };
+    use url::Url;
+
+    fn felt(hex: &str) -> FieldElement {
+        FieldElement::from_hex_be(hex).unwrap()
+    }
+
+    /// A provider which is never reached by the tests.
+    fn provider() -> AnyProvider {
+        AnyProvider::JsonRpcHttp(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://127.0.0.1:1").unwrap(),
+        )))
+    }
+
+    const CHAIN_ID: &str = "0x534e5f474f45524c49";
+    const PRIVATE_KEY: &str = "0x1234";
+
+    fn braavos_class() -> AccountClass {
+        AccountClass::Braavos {
+            class_hash: felt("0x4"),
+            mock_class_hash: felt("0x5"),
+            implementation: felt("0x6"),
+        }
+    }
+
+    fn factory(class: AccountClass) -> KiptAccountFactory {
+        futures::executor::block_on(KiptAccountFactory::new(
+            "test",
+            Some(felt(CHAIN_ID)),
+            class,
+            felt(PRIVATE_KEY),
+            provider(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn open_zeppelin_address() {
+        // Same vector as the `get_contract_address` test of starknet-rs: class
+        // 0x0750...5062 deployed with the salt 0x18a7...b9c8 and the calldata [0x1].
+        let class = AccountClass::OpenZeppelin {
+            class_hash: felt("0x0750cd490a7cd1572411169eaa8be292325990d33c5d4733655fe6b926985062"),
+        };
+
+        assert_eq!(
+            class.address(
+                FieldElement::ONE,
+                felt("0x0018a7a329d1d85b621350f2b5fc9c64b2e57dfe708525f0aff2c90de1e5b9c8"),
+            ),
+            felt("0x00da27ef7c3869c3a6cc6a0f7bf07a51c3e590825adba8a51cae27d815839eec")
+        );
+    }
+
+    #[test]
+    fn address_matches_the_deployment() {
+        let public_key = SigningKey::from_secret_scalar(felt(PRIVATE_KEY))
+            .verifying_key()
+            .scalar();
+        let salt = felt("0x42");
+
+        for class in [
+            AccountClass::OpenZeppelin {
+                class_hash: felt("0x1"),
+            },
+            AccountClass::Argent {
+                class_hash: felt("0x2"),
+                guardian: felt("0x3"),
+            },
+            braavos_class(),
+        ] {
+            let expected = class.address(public_key, salt);
+            let factory = factory(class);
+
+            assert_eq!(AccountDeployment::new(salt, &factory).address(), expected);
+        }
+    }
+
+    #[test]
+    fn argent_calldata() {
+        let class = AccountClass::Argent {
+            class_hash: felt("0x2"),
+            guardian: felt("0x3"),
+        };
+
+        assert_eq!(class.calldata(felt("0xa")), vec![felt("0xa"), felt("0x3")]);
+    }
+
+    #[test]
+    fn braavos_calldata() {
+        let class = braavos_class();
+        let public_key = felt("0xa");
+
+        assert_eq!(
+            class.calldata(public_key),
+            vec![
+                felt("0x5"),
+                get_selector_from_name("initializer").unwrap(),
+                FieldElement::ONE,
+                public_key,
+            ]
+        );
+    }
+
+    #[test]
+    fn braavos_signature() {
+        let KiptAccountFactory::Braavos(factory) = factory(braavos_class()) else {
+            panic!("Braavos factory expected");
+        };
+        let tx_hash = felt("0x123456");
+
+        let signature = futures::executor::block_on(factory.sign_with_aux_data(tx_hash)).unwrap();
+
+        // r, s, the implementation, and the 7 hardware signer fields.
+        assert_eq!(signature.len(), 10);
+        assert_eq!(signature[2], felt("0x6"));
+        assert!(signature[3..].iter().all(|f| *f == FieldElement::ZERO));
+
+        // The signed hash includes the auxiliary data.
+        let hashed = compute_hash_on_elements(&[&[tx_hash], &signature[2..]].concat());
+        let rs = Signature {
+            r: signature[0],
+            s: signature[1],
+        };
+        assert!(ecdsa_verify(&factory.public_key, &hashed, &rs).unwrap());
+        assert!(!ecdsa_verify(&factory.public_key, &tx_hash, &rs).unwrap());
+    }
+}
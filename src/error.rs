@@ -1,15 +1,18 @@
-use mlua::Error as LuaError;
+use mlua::{Error as LuaError, Table};
 use starknet::accounts::single_owner::SignError as AccountSignError;
-use starknet::accounts::AccountError;
+use starknet::accounts::{AccountError, AccountFactoryError};
 use starknet::core::types::contract::JsonError;
-use starknet::core::types::FromStrError;
+use starknet::core::types::{FieldElement, FromStrError, StarknetError};
 use starknet::core::utils::NonAsciiNameError;
-use starknet::providers::ProviderError;
+use starknet::providers::{MaybeUnknownErrorCode, ProviderError, StarknetErrorWithMessage};
 use starknet::signers::local_wallet::SignError;
+use starknet::signers::KeystoreError;
 use std::fmt;
 
 use thiserror::Error;
 
+use crate::lua::LuaTableSetable;
+
 /// Result type used for Kipt error management.
 pub type KiptResult<T, E = Error> = Result<T, E>;
 
@@ -31,36 +34,174 @@ pub enum Error {
     #[error(transparent)]
     Account(#[from] AccountError<AccountSignError<SignError>>),
     #[error(transparent)]
+    Keystore(#[from] KeystoreError),
+    #[error(transparent)]
+    AccountFactory(#[from] AccountFactoryError<SignError>),
+    #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
+    #[error("Missing global variable: {0}")]
+    MissingGlobal(String),
     #[error("Contract artifacts is missing: {0}")]
     ArtifactsMissing(String),
+    #[error("Contract artifacts are ambiguous: {0}")]
+    ArtifactsAmbiguous(String),
     #[error(transparent)]
     NonAsciiName(#[from] NonAsciiNameError),
+    #[error("ABI error: {0}")]
+    Abi(String),
+    #[error(transparent)]
+    Lua(#[from] LuaError),
+    #[error("Transaction 0x{transaction_hash:064x} reverted: {reason}")]
+    Reverted {
+        transaction_hash: FieldElement,
+        reason: String,
+    },
+}
+
+impl Error {
+    /// Returns the machine-readable kind of the error, exposed to Lua.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Other(_) | Error::Anyhow(_) => "other",
+            Error::StarknetProvider(_) => "provider",
+            Error::StarknetFromStr(_)
+            | Error::SerdeJson(_)
+            | Error::ContractJson(_)
+            | Error::NonAsciiName(_) => "parse",
+            Error::StdIO(_) => "io",
+            Error::Account(AccountError::Provider(_)) => "provider",
+            Error::Account(_) => "account",
+            Error::AccountFactory(AccountFactoryError::Provider(_)) => "provider",
+            Error::AccountFactory(_) => "account",
+            Error::Keystore(_) => "keystore",
+            Error::MissingGlobal(_) => "missing_global",
+            Error::ArtifactsMissing(_) => "artifacts_missing",
+            Error::ArtifactsAmbiguous(_) => "artifacts_ambiguous",
+            Error::Abi(_) => "abi",
+            Error::Lua(_) => "lua",
+            Error::Reverted { .. } => "reverted",
+        }
+    }
 }
 
 impl From<Error> for LuaError {
     fn from(e: Error) -> Self {
-        Self::ExternalError(std::sync::Arc::new(ErrorExtLua::new(&e.to_string())))
+        Self::ExternalError(std::sync::Arc::new(ErrorExtLua::from(&e)))
     }
 }
 
-// TODO: check if we can implement error for lua with enum!
+/// Returns a human readable message from a Lua error, without
+/// the callback wrapping added by mlua.
+///
+/// # Arguments
+///
+/// * `e` - The Lua error.
+pub fn lua_error_message(e: &LuaError) -> String {
+    ErrorExtLua::from(e).to_string()
+}
 
 /// This error type is mainly used to interact with mlua library,
-/// which is expecting a struct.
-#[derive(Debug, Clone)]
+/// which is expecting a struct. It keeps the details of the
+/// `Error` to be exposed to Lua as an error table.
+#[derive(Debug, Clone, Default)]
 pub struct ErrorExtLua {
+    kind: String,
     reason: String,
+    /// Starknet error code, if the error comes from the node.
+    code: Option<i64>,
+    /// Starknet error name, if the error code is known.
+    starknet_error: Option<String>,
+    revert_reason: Option<String>,
+    transaction_hash: Option<FieldElement>,
 }
 
 impl ErrorExtLua {
-    pub fn new(reason: &str) -> Self {
+    pub fn new(kind: &str, reason: &str) -> Self {
         Self {
+            kind: kind.to_string(),
             reason: reason.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the Starknet error code from a provider error, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `e` - The provider error.
+    fn with_provider_error(mut self, e: &ProviderError) -> Self {
+        if let ProviderError::StarknetError(StarknetErrorWithMessage { code, .. }) = e {
+            match code {
+                MaybeUnknownErrorCode::Known(c) => {
+                    self.code = Some(starknet_error_code(c));
+                    self.starknet_error = Some(format!("{:?}", c));
+                }
+                MaybeUnknownErrorCode::Unknown(c) => self.code = Some(*c),
+            }
+        }
+
+        self
+    }
+}
+
+impl From<&Error> for ErrorExtLua {
+    fn from(e: &Error) -> Self {
+        let ext = Self::new(e.kind(), &e.to_string());
+
+        match e {
+            // Keeps the kind of errors coming back from Lua.
+            Error::Lua(le) => Self::from(le),
+            Error::StarknetProvider(pe)
+            | Error::Account(AccountError::Provider(pe))
+            | Error::AccountFactory(AccountFactoryError::Provider(pe)) => {
+                ext.with_provider_error(pe)
+            }
+            Error::Reverted {
+                transaction_hash,
+                reason,
+            } => Self {
+                revert_reason: Some(reason.clone()),
+                transaction_hash: Some(*transaction_hash),
+                ..ext
+            },
+            _ => ext,
+        }
+    }
+}
+
+impl From<&LuaError> for ErrorExtLua {
+    fn from(e: &LuaError) -> Self {
+        match e {
+            LuaError::CallbackError { cause, .. } => Self::from(cause.as_ref()),
+            LuaError::ExternalError(ext) => match ext.downcast_ref::<ErrorExtLua>() {
+                Some(k) => k.clone(),
+                None => Self::new("other", &ext.to_string()),
+            },
+            _ => Self::new("lua", &e.to_string()),
         }
     }
 }
 
+impl LuaTableSetable for ErrorExtLua {
+    fn set_all(&self, table: &Table) {
+        table.set("kind", self.kind.clone()).unwrap();
+        table.set("message", self.reason.clone()).unwrap();
+        table.set("code", self.code).unwrap();
+        table
+            .set("starknet_error", self.starknet_error.clone())
+            .unwrap();
+        table
+            .set("revert_reason", self.revert_reason.clone())
+            .unwrap();
+        table
+            .set(
+                "tx_hash",
+                self.transaction_hash.map(|h| format!("0x{:064x}", h)),
+            )
+            .unwrap();
+    }
+}
+
 impl fmt::Display for ErrorExtLua {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.reason)
@@ -68,3 +209,41 @@ impl fmt::Display for ErrorExtLua {
 }
 
 impl std::error::Error for ErrorExtLua {}
+
+/// Returns the code of a known Starknet error, as defined
+/// in the JSON-RPC specification.
+///
+/// # Arguments
+///
+/// * `e` - The Starknet error.
+fn starknet_error_code(e: &StarknetError) -> i64 {
+    match e {
+        StarknetError::FailedToReceiveTransaction => 1,
+        StarknetError::NoTraceAvailable => 10,
+        StarknetError::ContractNotFound => 20,
+        StarknetError::BlockNotFound => 24,
+        StarknetError::InvalidTransactionHash => 25,
+        StarknetError::InvalidBlockHash => 26,
+        StarknetError::InvalidTransactionIndex => 27,
+        StarknetError::ClassHashNotFound => 28,
+        StarknetError::TransactionHashNotFound => 29,
+        StarknetError::PageSizeTooBig => 31,
+        StarknetError::NoBlocks => 32,
+        StarknetError::InvalidContinuationToken => 33,
+        StarknetError::TooManyKeysInFilter => 34,
+        StarknetError::ContractError => 40,
+        StarknetError::ClassAlreadyDeclared => 51,
+        StarknetError::InvalidTransactionNonce => 52,
+        StarknetError::InsufficientMaxFee => 53,
+        StarknetError::InsufficientAccountBalance => 54,
+        StarknetError::ValidationFailure => 55,
+        StarknetError::CompilationFailed => 56,
+        StarknetError::ContractClassSizeIsTooLarge => 57,
+        StarknetError::NonAccount => 58,
+        StarknetError::DuplicateTx => 59,
+        StarknetError::CompiledClassHashMismatch => 60,
+        StarknetError::UnsupportedTxVersion => 61,
+        StarknetError::UnsupportedContractClassVersion => 62,
+        StarknetError::UnexpectedError => 63,
+    }
+}
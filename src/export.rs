@@ -0,0 +1,261 @@
+//! Export of signed transactions to a file instead of sending them.
+//!
+//! The transactions are written in the shape expected by the
+//! `starknet_add*Transaction` RPC methods, to be sent later
+//! with `kipt broadcast`.
+use async_trait::async_trait;
+use starknet::{
+    accounts::{AccountError, ConnectedAccount, Declaration, Execution, LegacyDeclaration},
+    core::types::{BroadcastedDeclareTransaction, BroadcastedTransaction, FieldElement},
+};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, KiptResult};
+use crate::fee::{self, DryRun, FeeOutput};
+use crate::nonce;
+use crate::transaction::TxOptions;
+
+/// A transaction builder which can be signed to be exported.
+#[async_trait]
+pub trait Exportable: DryRun {
+    /// Signs the transaction with the given max fee.
+    /// Returns the signed transaction and its hash.
+    async fn sign(
+        self,
+        max_fee: FieldElement,
+    ) -> KiptResult<(BroadcastedTransaction, FieldElement)>;
+}
+
+#[async_trait]
+impl<A> Exportable for Execution<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    async fn sign(
+        self,
+        max_fee: FieldElement,
+    ) -> KiptResult<(BroadcastedTransaction, FieldElement)> {
+        let prepared = self
+            .max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?;
+
+        let request = prepared
+            .get_invoke_request(false)
+            .await
+            .map_err(AccountError::Signing)?;
+
+        Ok((
+            BroadcastedTransaction::Invoke(request),
+            prepared.transaction_hash(false),
+        ))
+    }
+}
+
+#[async_trait]
+impl<A> Exportable for Declaration<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    async fn sign(
+        self,
+        max_fee: FieldElement,
+    ) -> KiptResult<(BroadcastedTransaction, FieldElement)> {
+        let prepared = self
+            .max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?;
+
+        let request = prepared.get_declare_request(false).await?;
+
+        Ok((
+            BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(request)),
+            prepared.transaction_hash(false),
+        ))
+    }
+}
+
+#[async_trait]
+impl<A> Exportable for LegacyDeclaration<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    async fn sign(
+        self,
+        max_fee: FieldElement,
+    ) -> KiptResult<(BroadcastedTransaction, FieldElement)> {
+        let prepared = self
+            .max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?;
+
+        let request = prepared.get_declare_request(false).await?;
+
+        let transaction_hash = prepared
+            .transaction_hash(false)
+            .map_err(|e| Error::Other(e.to_string()))?;
+
+        Ok((
+            BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V1(request)),
+            transaction_hash,
+        ))
+    }
+}
+
+/// Signs a transaction (invoke, deploy or declaration) and appends it
+/// to the export file. Returns the hash and the nonce of the exported transaction.
+///
+/// # Arguments
+///
+/// * `account` - The account signing the transaction.
+/// * `tx` - The transaction to export.
+/// * `nonce` - Nonce of the transaction, fetched if `None`.
+/// * `tx_options` - Options of the transaction.
+/// * `path` - Path of the export file.
+pub async fn export<A, T>(
+    account: &A,
+    tx: T,
+    nonce: Option<FieldElement>,
+    tx_options: &TxOptions,
+    path: &Path,
+) -> KiptResult<(FieldElement, FieldElement)>
+where
+    A: ConnectedAccount + Sync,
+    T: Exportable,
+{
+    let nonce = nonce::or_fetch(account, nonce).await?;
+    let tx = tx.with_nonce(nonce);
+
+    let max_fee = match tx_options.max_fee {
+        Some(m) => m,
+        None => {
+            let fee = FeeOutput::from((tx.estimate().await?, false));
+            fee::max_fee(tx_options, fee.overall_fee)
+        }
+    };
+
+    let (transaction, transaction_hash) = tx.sign(max_fee).await?;
+
+    append(path, &transaction)?;
+
+    Ok((transaction_hash, nonce))
+}
+
+/// Appends a signed transaction to the export file,
+/// which is created if it doesn't exist.
+///
+/// # Arguments
+///
+/// * `path` - Path of the export file.
+/// * `transaction` - The signed transaction.
+fn append(path: &Path, transaction: &BroadcastedTransaction) -> KiptResult<()> {
+    let mut transactions: Vec<serde_json::Value> = if path.exists() {
+        serde_json::from_reader(fs::File::open(path)?)?
+    } else {
+        vec![]
+    };
+
+    transactions.push(serde_json::to_value(transaction)?);
+
+    fs::write(path, serde_json::to_string_pretty(&transactions)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::{
+        accounts::{Account, Call, ExecutionEncoding, SingleOwnerAccount},
+        core::types::contract::SierraClass,
+        providers::{jsonrpc::HttpTransport, AnyProvider, JsonRpcClient},
+        signers::{LocalWallet, SigningKey},
+    };
+    use std::sync::Arc;
+    use url::Url;
+
+    fn felt(hex: &str) -> FieldElement {
+        FieldElement::from_hex_be(hex).unwrap()
+    }
+
+    /// An account whose provider is never reached, as the nonce
+    /// and the max fee are given.
+    fn account() -> SingleOwnerAccount<AnyProvider, LocalWallet> {
+        let provider = AnyProvider::JsonRpcHttp(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://127.0.0.1:1").unwrap(),
+        )));
+
+        SingleOwnerAccount::new(
+            provider,
+            LocalWallet::from(SigningKey::from_secret_scalar(felt("0x1234"))),
+            felt("0x1111"),
+            felt("0x534e5f474f45524c49"),
+            ExecutionEncoding::New,
+        )
+    }
+
+    #[test]
+    fn exported_file_parses_as_broadcasted_transactions() {
+        let path = std::env::temp_dir().join(format!("kipt-export-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let account = account();
+        let tx_options = TxOptions {
+            max_fee: Some(felt("0x1000")),
+            ..Default::default()
+        };
+
+        let sierra: SierraClass = serde_json::from_str(
+            &fs::read_to_string("contracts/artifacts/c1.sierra.json").unwrap(),
+        )
+        .unwrap();
+
+        let (invoke_hash, declare_hash) = futures::executor::block_on(async {
+            let execution = account.execute(vec![Call {
+                to: felt("0x2222"),
+                selector: felt("0x3333"),
+                calldata: vec![felt("0x1")],
+            }]);
+            let (invoke_hash, nonce) =
+                export(&account, execution, Some(felt("0x5")), &tx_options, &path)
+                    .await
+                    .unwrap();
+            assert_eq!(nonce, felt("0x5"));
+
+            let declaration = account.declare(Arc::new(sierra.flatten().unwrap()), felt("0x4444"));
+            let (declare_hash, _) =
+                export(&account, declaration, Some(felt("0x6")), &tx_options, &path)
+                    .await
+                    .unwrap();
+
+            (invoke_hash, declare_hash)
+        });
+        assert_ne!(invoke_hash, declare_hash);
+
+        let transactions: Vec<BroadcastedTransaction> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        match &transactions[0] {
+            BroadcastedTransaction::Invoke(t) => {
+                assert_eq!(t.nonce, felt("0x5"));
+                assert_eq!(t.max_fee, felt("0x1000"));
+                assert_eq!(t.sender_address, felt("0x1111"));
+                assert_eq!(t.signature.len(), 2);
+            }
+            t => panic!("Expected an invoke transaction, got {:?}", t),
+        }
+        match &transactions[1] {
+            BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(t)) => {
+                assert_eq!(t.nonce, felt("0x6"));
+                assert_eq!(t.compiled_class_hash, felt("0x4444"));
+            }
+            t => panic!("Expected a declare v2 transaction, got {:?}", t),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Fee estimation of the transactions, also used to dry-run
+//! the transactions without sending them.
+use async_trait::async_trait;
+use mlua::{Lua, Result as LuaResult, Table};
+use starknet::{
+    accounts::{
+        AccountDeployment, AccountError, AccountFactory, AccountFactoryError, ConnectedAccount,
+        Declaration, Execution, LegacyDeclaration,
+    },
+    core::types::{
+        ExecuteInvocation, FeeEstimate, FieldElement, SimulatedTransaction, StarknetError,
+        TransactionTrace,
+    },
+    providers::{
+        MaybeUnknownErrorCode, ProviderError, SequencerGatewayProviderError,
+        StarknetErrorWithMessage,
+    },
+};
+use tracing::trace;
+
+use crate::error::{Error, KiptResult};
+use crate::invoke::{self, InvokeCall};
+use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
+use crate::transaction::TxOptions;
+use crate::{account, logger};
+
+/// Multiplier applied to the estimated fee to compute the max fee,
+/// same as the default of starknet-rs.
+const FEE_ESTIMATE_MULTIPLIER: f64 = 1.1;
+
+/// JSON-RPC error code of an unknown method.
+const JSONRPC_METHOD_NOT_FOUND: i64 = -32601;
+
+/// Fee output.
+#[derive(Debug, Clone)]
+pub struct FeeOutput {
+    pub overall_fee: u64,
+    pub gas_consumed: u64,
+    pub gas_price: u64,
+    /// True if the fee was obtained by simulating the transaction,
+    /// false if only estimated.
+    pub simulated: bool,
+}
+
+impl From<(FeeEstimate, bool)> for FeeOutput {
+    fn from((fee, simulated): (FeeEstimate, bool)) -> Self {
+        Self {
+            overall_fee: fee.overall_fee,
+            gas_consumed: fee.gas_consumed,
+            gas_price: fee.gas_price,
+            simulated,
+        }
+    }
+}
+
+impl LuaTableSetable for FeeOutput {
+    fn set_all(&self, table: &Table) {
+        table.set("overall_fee", self.overall_fee).unwrap();
+        table.set("gas_consumed", self.gas_consumed).unwrap();
+        table.set("gas_price", self.gas_price).unwrap();
+        table.set("simulated", self.simulated).unwrap();
+    }
+}
+
+/// Defines a lua function that estimates the fee of an invoke transaction.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `calls` - Calls to be added in the invoke transaction.
+/// * `options` - Options for the invoke transaction.
+pub fn lua_estimate_fee<'lua>(
+    lua: &'lua Lua,
+    calls: Vec<Table<'lua>>,
+    options: Table<'lua>,
+) -> LuaResult<Table<'lua>> {
+    let (url_network, address, signer, is_legacy) = lua::get_account(lua)?;
+    let chain_id = lua::get_chain_id(lua)?;
+
+    let calls = calls
+        .iter()
+        .map(|c| InvokeCall::from_table(lua, c, &options))
+        .collect::<LuaResult<Vec<InvokeCall>>>()?;
+
+    let mut out_log = String::from(&format!("> estimate_fee: ({})\n", calls.len()));
+    for (i, c) in calls.iter().enumerate() {
+        out_log.push_str(&format!("call #{} -> {} {}\n", i, c.to, c.func));
+    }
+
+    let data = futures::executor::block_on(async move {
+        RT.spawn(async move {
+            let account =
+                match account::setup_account(&url_network, &address, signer, is_legacy, chain_id)
+                    .await
+                {
+                    Ok(a) => a,
+                    Err(e) => {
+                        return LuaOutput {
+                            data: None,
+                            error: Some(e),
+                        }
+                    }
+                };
+
+            match estimate_invoke_fee(account, calls).await {
+                Ok(fee) => LuaOutput {
+                    data: Some(fee),
+                    error: None,
+                },
+                Err(e) => LuaOutput {
+                    data: None,
+                    error: Some(e),
+                },
+            }
+        })
+        .await
+        .unwrap()
+    });
+
+    if let Some(d) = data.data {
+        let t = lua.create_table()?;
+        d.set_all(&t);
+
+        out_log.push_str(&format!("|   overall_fee    |  {}  |\n", d.overall_fee));
+        logger::write(lua, &out_log)?;
+
+        Ok(t)
+    } else {
+        Err(lua::output_error(data.error))
+    }
+}
+
+/// Estimates the fee of an invoke transaction.
+///
+/// # Arguments
+///
+/// * `account` - The account that would send the transaction.
+/// * `calls` - The list of calls to be executed.
+async fn estimate_invoke_fee<A>(account: A, calls: Vec<InvokeCall>) -> KiptResult<FeeOutput>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    let fee = account
+        .execute(invoke::to_starknet_calls(calls)?)
+        .estimate_fee()
+        .await?;
+
+    Ok(FeeOutput::from((fee, false)))
+}
+
+/// A transaction builder which can be dry-run: the transaction is simulated
+/// (or its fee estimated) and its hash computed without sending it.
+#[async_trait]
+pub trait DryRun: Sized + Send + Sync {
+    /// Sets the nonce of the transaction.
+    fn with_nonce(self, nonce: FieldElement) -> Self;
+
+    /// Simulates the transaction, skipping the fee charge
+    /// as the max fee is not known yet.
+    async fn simulate_uncharged(&self) -> KiptResult<SimulatedTransaction>;
+
+    /// Estimates the fee of the transaction.
+    async fn estimate(&self) -> KiptResult<FeeEstimate>;
+
+    /// Returns the hash the transaction would have if sent with the given max fee.
+    fn hash_with_max_fee(self, max_fee: FieldElement) -> KiptResult<FieldElement>;
+}
+
+#[async_trait]
+impl<A> DryRun for Execution<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    fn with_nonce(self, nonce: FieldElement) -> Self {
+        self.nonce(nonce)
+    }
+
+    async fn simulate_uncharged(&self) -> KiptResult<SimulatedTransaction> {
+        Ok(self.simulate(false, true).await?)
+    }
+
+    async fn estimate(&self) -> KiptResult<FeeEstimate> {
+        Ok(self.estimate_fee().await?)
+    }
+
+    fn hash_with_max_fee(self, max_fee: FieldElement) -> KiptResult<FieldElement> {
+        Ok(self
+            .max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .transaction_hash(false))
+    }
+}
+
+#[async_trait]
+impl<A> DryRun for Declaration<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    fn with_nonce(self, nonce: FieldElement) -> Self {
+        self.nonce(nonce)
+    }
+
+    async fn simulate_uncharged(&self) -> KiptResult<SimulatedTransaction> {
+        Ok(self.simulate(false, true).await?)
+    }
+
+    async fn estimate(&self) -> KiptResult<FeeEstimate> {
+        Ok(self.estimate_fee().await?)
+    }
+
+    fn hash_with_max_fee(self, max_fee: FieldElement) -> KiptResult<FieldElement> {
+        Ok(self
+            .max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .transaction_hash(false))
+    }
+}
+
+#[async_trait]
+impl<A> DryRun for LegacyDeclaration<'_, A>
+where
+    A: ConnectedAccount + Sync,
+    Error: From<AccountError<A::SignError>>,
+{
+    fn with_nonce(self, nonce: FieldElement) -> Self {
+        self.nonce(nonce)
+    }
+
+    async fn simulate_uncharged(&self) -> KiptResult<SimulatedTransaction> {
+        Ok(self.simulate(false, true).await?)
+    }
+
+    async fn estimate(&self) -> KiptResult<FeeEstimate> {
+        Ok(self.estimate_fee().await?)
+    }
+
+    fn hash_with_max_fee(self, max_fee: FieldElement) -> KiptResult<FieldElement> {
+        self.max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .transaction_hash(false)
+            .map_err(|e| Error::Other(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl<F> DryRun for AccountDeployment<'_, F>
+where
+    F: AccountFactory + Sync,
+    Error: From<AccountFactoryError<F::SignError>>,
+{
+    fn with_nonce(self, nonce: FieldElement) -> Self {
+        self.nonce(nonce)
+    }
+
+    async fn simulate_uncharged(&self) -> KiptResult<SimulatedTransaction> {
+        Ok(self.simulate(false, true).await?)
+    }
+
+    async fn estimate(&self) -> KiptResult<FeeEstimate> {
+        Ok(self.estimate_fee().await?)
+    }
+
+    fn hash_with_max_fee(self, max_fee: FieldElement) -> KiptResult<FieldElement> {
+        Ok(self
+            .max_fee(max_fee)
+            .prepared()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .transaction_hash())
+    }
+}
+
+/// Dry-runs a transaction: the transaction is simulated if the node
+/// supports it, else only the fee is estimated.
+/// Returns the hash the transaction would have if sent, and the fee.
+/// Fails with `Error::Reverted` if the simulated execution reverted.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction to dry-run.
+/// * `nonce` - The nonce of the transaction.
+/// * `tx_options` - Options of the transaction.
+pub async fn dry_run<T: DryRun>(
+    tx: T,
+    nonce: FieldElement,
+    tx_options: &TxOptions,
+) -> KiptResult<(FieldElement, FeeOutput)> {
+    let tx = tx.with_nonce(nonce);
+
+    let (fee, reverted) = match tx.simulate_uncharged().await {
+        Ok(s) => (
+            FeeOutput::from((s.fee_estimation, true)),
+            revert_reason(&s.transaction_trace),
+        ),
+        Err(e) if is_simulation_unsupported(&e) => {
+            trace!("Simulation not supported, estimating fee only: {:?}", e);
+            (FeeOutput::from((tx.estimate().await?, false)), None)
+        }
+        Err(e) => {
+            // The execution failed before any fee could be estimated.
+            return match contract_error(&e) {
+                Some(reason) => Err(Error::Reverted {
+                    transaction_hash: tx.hash_with_max_fee(max_fee(tx_options, 0))?,
+                    reason,
+                }),
+                None => Err(e),
+            };
+        }
+    };
+
+    let tx_hash = tx.hash_with_max_fee(max_fee(tx_options, fee.overall_fee))?;
+
+    if let Some(reason) = reverted {
+        return Err(Error::Reverted {
+            transaction_hash: tx_hash,
+            reason,
+        });
+    }
+
+    Ok((tx_hash, fee))
+}
+
+/// Returns the revert reason of a simulated transaction, if its execution reverted.
+///
+/// # Arguments
+///
+/// * `trace` - The trace of the simulated transaction.
+fn revert_reason(trace: &TransactionTrace) -> Option<String> {
+    match trace {
+        TransactionTrace::Invoke(t) => match &t.execute_invocation {
+            ExecuteInvocation::Reverted(r) => Some(r.revert_reason.clone()),
+            ExecuteInvocation::Success(_) => None,
+        },
+        // The traces of the other transactions have no reverted execution:
+        // a failing constructor of an account deployment fails the simulation
+        // with a contract error instead (see `contract_error`).
+        TransactionTrace::DeployAccount(_)
+        | TransactionTrace::Declare(_)
+        | TransactionTrace::L1Handler(_) => None,
+    }
+}
+
+/// Returns the provider error of a transaction, if any.
+///
+/// # Arguments
+///
+/// * `e` - The error of the transaction.
+fn provider_error(e: &Error) -> Option<&ProviderError> {
+    match e {
+        Error::StarknetProvider(pe)
+        | Error::Account(AccountError::Provider(pe))
+        | Error::AccountFactory(AccountFactoryError::Provider(pe)) => Some(pe),
+        _ => None,
+    }
+}
+
+/// Returns true if the error reports that the node doesn't support
+/// the simulation of the transactions.
+///
+/// # Arguments
+///
+/// * `e` - The error of the simulation.
+fn is_simulation_unsupported(e: &Error) -> bool {
+    match provider_error(e) {
+        Some(ProviderError::StarknetError(StarknetErrorWithMessage {
+            code: MaybeUnknownErrorCode::Unknown(JSONRPC_METHOD_NOT_FOUND),
+            ..
+        })) => true,
+        Some(ProviderError::Other(o)) => matches!(
+            o.as_any().downcast_ref::<SequencerGatewayProviderError>(),
+            Some(
+                SequencerGatewayProviderError::MethodNotSupported
+                    | SequencerGatewayProviderError::UnsupportedSimulationFlag
+            )
+        ),
+        _ => false,
+    }
+}
+
+/// Returns the message of the error if the execution of the contract
+/// failed during the simulation, like a reverting account constructor.
+///
+/// # Arguments
+///
+/// * `e` - The error of the simulation.
+fn contract_error(e: &Error) -> Option<String> {
+    match provider_error(e) {
+        Some(ProviderError::StarknetError(StarknetErrorWithMessage {
+            code: MaybeUnknownErrorCode::Known(StarknetError::ContractError),
+            message,
+        })) => Some(message.clone()),
+        _ => None,
+    }
+}
+
+/// Computes the max fee the transaction would be sent with:
+/// the max fee from the options if any, else the estimated overall
+/// fee with the multiplier applied.
+///
+/// # Arguments
+///
+/// * `tx_options` - Options of the transaction.
+/// * `overall_fee` - The estimated overall fee.
+pub fn max_fee(tx_options: &TxOptions, overall_fee: u64) -> FieldElement {
+    tx_options.max_fee.unwrap_or_else(|| {
+        let multiplier = tx_options.fee_multiplier.unwrap_or(FEE_ESTIMATE_MULTIPLIER);
+
+        FieldElement::from((overall_fee as f64 * multiplier) as u64)
+    })
+}
@@ -1,53 +1,60 @@
-use mlua::{Error as LuaError, FromLua, Lua, Result as LuaResult, Table, Value};
+use mlua::{Lua, Result as LuaResult, Table};
 use starknet::{
-    accounts::{Account, Call, ConnectedAccount, SingleOwnerAccount},
-    core::types::{FieldElement, InvokeTransactionResult},
+    accounts::{Account, Call, ConnectedAccount},
+    core::types::FieldElement,
     core::utils::get_selector_from_name,
-    providers::AnyProvider,
-    signers::LocalWallet,
 };
+use std::sync::Arc;
 
-use std::time::Duration;
-
-use crate::error::{ErrorExtLua, KiptResult};
+use crate::account::KiptAccount;
+use crate::error::KiptResult;
 use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
-use crate::{account, logger, transaction};
+use crate::transaction::{TxOptions, TxOutcome};
+use crate::{account, export, fee, logger, nonce, state, transaction};
 
 /// Invoke call.
-/// TODO: implement the FromLua trait.
 pub struct InvokeCall {
     pub to: String,
     pub func: String,
     pub calldata: Vec<String>,
 }
 
-impl<'lua> FromLua<'lua> for InvokeCall {
-    fn from_lua(value: Value<'lua>, _lua: &'lua Lua) -> LuaResult<Self> {
-        if let Value::Table(t) = value {
-            Ok(InvokeCall {
-                to: t.get("to")?,
-                func: t.get("func")?,
-                calldata: t.get("calldata")?,
-            })
+impl InvokeCall {
+    /// Builds an invoke call from a lua table.
+    /// If the call (or the invoke options) contains the "abi" key,
+    /// the calldata are encoded using the contract ABI.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - Lua VM instance.
+    /// * `table` - Lua table with "to", "func" and "calldata" keys.
+    /// * `options` - Options of the invoke transaction.
+    pub fn from_table(lua: &Lua, table: &Table, options: &Table) -> LuaResult<Self> {
+        let func: String = table.get("func")?;
+        let calldata: Table = table.get("calldata")?;
+
+        let abi = if table.contains_key("abi")? {
+            lua::get_abi_from_options(lua, table)?
         } else {
-            Err(LuaError::ExternalError(std::sync::Arc::new(
-                ErrorExtLua::new(&format!(
-                    "Can't convert the value {:?} into InvokeCall",
-                    value
-                )),
-            )))
-        }
+            lua::get_abi_from_options(lua, options)?
+        };
+
+        Ok(InvokeCall {
+            to: table.get("to")?,
+            calldata: lua::encode_args(abi.as_ref(), |abi| abi.function_inputs(&func), &calldata)?,
+            func,
+        })
     }
 }
 
 /// Invoke output.
 struct InvokeOutput {
-    pub transaction_hash: String,
+    pub transaction: TxOutcome,
 }
 
 impl LuaTableSetable for InvokeOutput {
     fn set_all(&self, table: &Table) {
-        table.set("tx_hash", self.transaction_hash.clone()).unwrap();
+        self.transaction.set_all(table);
     }
 }
 
@@ -60,41 +67,58 @@ impl LuaTableSetable for InvokeOutput {
 /// * `options` - Options for the invoke transaction.
 pub fn lua_invoke<'lua>(
     lua: &'lua Lua,
-    calls: Vec<InvokeCall>,
+    calls: Vec<Table<'lua>>,
     options: Table<'lua>,
 ) -> LuaResult<Table<'lua>> {
-    let (url_network, address, privkey, is_legacy) = lua::get_account(lua)?;
+    let calls = calls
+        .iter()
+        .map(|c| InvokeCall::from_table(lua, c, &options))
+        .collect::<LuaResult<Vec<InvokeCall>>>()?;
 
-    let watch_interval = lua::get_watch_from_options(&options)?;
+    let tx_options = lua::get_tx_options(lua, &options)?;
+    let label: Option<String> = options.get("label")?;
 
-    let mut out_log = String::from(&format!("> invoke: ({})\\n", calls.len()));
+    let mut out_log = String::from(&format!("> invoke: ({})\n", calls.len()));
     for (i, c) in calls.iter().enumerate() {
-        out_log.push_str(&format!("call #{} -> {} {}\\n", i, c.to, c.func));
+        out_log.push_str(&format!("call #{} -> {} {}\n", i, c.to, c.func));
+    }
+
+    if let Some(t) = state::lookup(lua, &label)? {
+        out_log.push_str(&format!(
+            "skipped: `{}` found in state\n",
+            label.unwrap_or_default()
+        ));
+        logger::write(lua, &out_log)?;
+
+        return Ok(t);
     }
 
+    let (url_network, address, signer, is_legacy) = lua::get_account(lua)?;
+    let chain_id = lua::get_chain_id(lua)?;
+
     let data = futures::executor::block_on(async move {
         RT.spawn(async move {
             let account =
-                match account::setup_account(&url_network, &address, &privkey, is_legacy).await {
+                match account::setup_account(&url_network, &address, signer, is_legacy, chain_id)
+                    .await
+                {
                     Ok(a) => a,
                     Err(e) => {
                         return LuaOutput {
                             data: None,
-                            error: format!("{:?}", e),
+                            error: Some(e),
                         }
                     }
                 };
 
-            match invoke_tx(account, calls, watch_interval).await {
-                Ok(invk_res) => LuaOutput {
-                    data: Some(InvokeOutput {
-                        transaction_hash: format!("0x{:064x}", invk_res.transaction_hash),
-                    }),
-                    error: "".to_string(),
+            match invoke_tx(account, calls, &tx_options).await {
+                Ok(transaction) => LuaOutput {
+                    data: Some(InvokeOutput { transaction }),
+                    error: None,
                 },
                 Err(e) => LuaOutput {
                     data: None,
-                    error: format!("{:?}", e),
+                    error: Some(e),
                 },
             }
         })
@@ -106,19 +130,19 @@ pub fn lua_invoke<'lua>(
         let t = lua.create_table()?;
         d.set_all(&t);
 
-        out_log.push_str(&format!(
-            "|     tx_hash      |  {}  |\\n",
-            d.transaction_hash
-        ));
+        d.transaction.log(&mut out_log);
         logger::write(lua, &out_log)?;
 
+        state::record(lua, &label, "invoke", &t)?;
+        nonce::track(lua, Some(&d.transaction))?;
+
         Ok(t)
     } else {
-        out_log.push_str(&format!("error: {}\\n", data.error));
+        nonce::track(lua, None)?;
+        let e = lua::output_error(data.error);
+        out_log.push_str(&format!("error: {}\n", e));
 
-        Err(LuaError::ExternalError(std::sync::Arc::new(
-            ErrorExtLua::new(&data.error),
-        )))
+        Err(e)
     }
 }
 
@@ -128,14 +152,72 @@ pub fn lua_invoke<'lua>(
 ///
 /// * `account` - The account used to sign and send the transaction.
 /// * `calls` - The list of calls to be executed.
-/// * `watch_interval` - Watch interval for the transaction receipt.
+/// * `tx_options` - Options of the transaction.
 async fn invoke_tx(
-    account: SingleOwnerAccount<AnyProvider, LocalWallet>,
+    account: Arc<KiptAccount>,
     calls: Vec<InvokeCall>,
-    watch_interval: Option<Duration>,
-) -> KiptResult<InvokeTransactionResult> {
-    // TODO: add fee estimate.
+    tx_options: &TxOptions,
+) -> KiptResult<TxOutcome> {
+    let mut execution = account.execute(to_starknet_calls(calls)?);
+
+    let nonce = nonce::resolve(&account, tx_options).await?;
+    if let Some(n) = nonce {
+        execution = execution.nonce(n);
+    }
+
+    if let Some(max_fee) = tx_options.max_fee {
+        execution = execution.max_fee(max_fee);
+    }
+
+    if let Some(multiplier) = tx_options.fee_multiplier {
+        execution = execution.fee_estimate_multiplier(multiplier);
+    }
+
+    if tx_options.dry_run {
+        let nonce = nonce::or_fetch(&account, nonce).await?;
+        let (transaction_hash, fee) = fee::dry_run(execution, nonce, tx_options).await?;
 
+        return Ok(TxOutcome {
+            transaction_hash,
+            nonce: Some(nonce),
+            fee: Some(fee),
+            exported: false,
+        });
+    }
+
+    if let Some(path) = &tx_options.export {
+        let (transaction_hash, nonce) =
+            export::export(&account, execution, nonce, tx_options, path).await?;
+
+        return Ok(TxOutcome {
+            transaction_hash,
+            nonce: Some(nonce),
+            fee: None,
+            exported: true,
+        });
+    }
+
+    let invk_res = execution.send().await?;
+
+    if let Some(interval) = tx_options.watch_interval {
+        transaction::poll_exec_succeeded(account.provider(), invk_res.transaction_hash, interval)
+            .await?;
+    }
+
+    Ok(TxOutcome {
+        transaction_hash: invk_res.transaction_hash,
+        nonce,
+        fee: None,
+        exported: false,
+    })
+}
+
+/// Converts invoke calls into starknet calls.
+///
+/// # Arguments
+///
+/// * `calls` - The list of calls to be converted.
+pub fn to_starknet_calls(calls: Vec<InvokeCall>) -> KiptResult<Vec<Call>> {
     let mut sn_calls = vec![];
 
     for c in calls {
@@ -154,12 +236,5 @@ async fn invoke_tx(
         });
     }
 
-    let invk_res = account.execute(sn_calls).send().await?;
-
-    if let Some(interval) = watch_interval {
-        transaction::poll_exec_succeeded(account.provider(), invk_res.transaction_hash, interval)
-            .await?;
-    }
-
-    Ok(invk_res)
+    Ok(sn_calls)
 }
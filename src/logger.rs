@@ -1,7 +1,7 @@
 //! A simple logger used from lua,
 //! but initialization is opaque for the user.
 use chrono::Utc;
-use mlua::{Lua, Result as LuaResult, Value};
+use mlua::{Function, IntoLuaMulti, Lua, Result as LuaResult, Table, Value};
 
 pub fn setup(lua: &Lua) -> LuaResult<()> {
     lua.globals().set(
@@ -15,31 +15,82 @@ pub fn setup(lua: &Lua) -> LuaResult<()> {
 
             let date = Utc::now().format("%A, %B %e, %Y %H:%M:%S").to_string();
             let logfile = file_name.unwrap_or("kipt.out".to_string());
-            lua.load(format!(
-                "__INTERNAL_LOGGER__ = io.open(\"{}\", \"w\")
-__INTERNAL_LOGGER__:write(\"-- {} --\", \"\\n\\n\")
-",
-                logfile, date,
-            ))
-            .exec()?;
-
-            let logger: mlua::Value = lua.globals().get("__INTERNAL_LOGGER__")?;
-            Ok(logger)
+            let io: Table = lua.globals().get("io")?;
+            let open: Function = io.get("open")?;
+            let file: Value = open.call((logfile, "w"))?;
+            call_file_method(lua, &file, "write", (format!("-- {} --", date), "\n\n"))?;
+
+            lua.globals().set("__INTERNAL_LOGGER__", file.clone())?;
+            Ok(file)
         })?,
     )?;
 
     Ok(())
 }
 
+/// Writes data into the log file. Does nothing if the logger
+/// was not initialized with `logger_init`.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `data` - Data to be written.
 pub fn write(lua: &Lua, data: &str) -> LuaResult<()> {
-    lua.load(format!("__INTERNAL_LOGGER__:write(\"{}\", \"\\n\")", data))
-        .exec()?;
+    if !is_initialized(lua)? {
+        return Ok(());
+    }
 
-    Ok(())
+    let logger: Value = lua.globals().get("__INTERNAL_LOGGER__")?;
+    call_file_method(lua, &logger, "write", (data, "\n"))
 }
 
+/// Closes the log file, if the logger was initialized.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
 pub fn close(lua: &Lua) -> LuaResult<()> {
-    lua.load("__INTERNAL_LOGGER__:close()").exec()?;
+    if !is_initialized(lua)? {
+        return Ok(());
+    }
 
-    Ok(())
+    let logger: Value = lua.globals().get("__INTERNAL_LOGGER__")?;
+    call_file_method(lua, &logger, "close", ())
+}
+
+/// Calls a method of a file opened with the Lua `io` library.
+/// The file is a userdata not created by mlua, its methods
+/// are then taken from its metatable.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `file` - The file handle.
+/// * `name` - Name of the method.
+/// * `args` - Arguments of the method, after the file itself.
+fn call_file_method<'lua>(
+    lua: &'lua Lua,
+    file: &Value<'lua>,
+    name: &str,
+    args: impl IntoLuaMulti<'lua>,
+) -> LuaResult<()> {
+    let getmetatable: Function = lua.globals().get("getmetatable")?;
+    let metatable: Table = getmetatable.call(file.clone())?;
+    let methods: Table = metatable.get("__index")?;
+    let method: Function = methods.get(name)?;
+
+    let mut args = args.into_lua_multi(lua)?;
+    args.push_front(file.clone());
+
+    method.call(args)
+}
+
+/// Returns true if the logger was initialized with `logger_init`.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+fn is_initialized(lua: &Lua) -> LuaResult<bool> {
+    let logger: Value = lua.globals().get("__INTERNAL_LOGGER__")?;
+    Ok(logger != Value::Nil)
 }
@@ -1,9 +1,26 @@
 use lazy_static::lazy_static;
-use mlua::{Function, Lua, Number, Result as LuaResult, Table};
+use mlua::{
+    Error as LuaError, FromLuaMulti, Function, IntoLua, Lua, MultiValue, Number,
+    Result as LuaResult, Table, Value,
+};
+use starknet::core::types::{contract::AbiNamedMember, FieldElement};
+use starknet::core::utils::cairo_short_string_to_felt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::runtime::{Builder, Runtime};
 
-use crate::{call, declare, deploy, invoke, invoke::InvokeCall, logger, transaction};
+use crate::abi::ContractAbi;
+use crate::account::AccountDescriptor;
+use crate::args::{self, Args};
+use crate::config::Profile;
+use crate::error::{Error, ErrorExtLua, KiptResult};
+use crate::signer::KiptSigner;
+use crate::transaction::TxOptions;
+use crate::{
+    call, declare, deploy, deploy_account, fee, invoke, logger, nonce, scarb, signer, state,
+    transaction,
+};
 
 /// A simple trait to ensure that all
 /// data returned from a lua function can be serialized
@@ -20,7 +37,98 @@ impl LuaTableSetable for () {
 /// wrapping a rust function for starknet.
 pub struct LuaOutput<T: LuaTableSetable + Send> {
     pub data: Option<T>,
-    pub error: String,
+    pub error: Option<Error>,
+}
+
+/// Converts the error of a `LuaOutput` without data into a Lua error.
+///
+/// # Arguments
+///
+/// * `error` - The error of the output.
+pub fn output_error(error: Option<Error>) -> LuaError {
+    error
+        .unwrap_or_else(|| Error::Other("No data returned".to_string()))
+        .into()
+}
+
+/// Converts the result of a Starknet function into the convention
+/// used by all the functions exposed to Lua:
+/// `(value, nil)` on success and `(nil, error_table)` on failure.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `result` - The result of the function.
+fn to_lua_multi<'lua, T: IntoLua<'lua>>(
+    lua: &'lua Lua,
+    result: LuaResult<T>,
+) -> LuaResult<(Value<'lua>, Value<'lua>)> {
+    match result {
+        Ok(v) => Ok((v.into_lua(lua)?, Value::Nil)),
+        Err(e) => Ok((Value::Nil, Value::Table(error_table(lua, &e)?))),
+    }
+}
+
+/// Converts the arguments given by the script and calls a Starknet function with them.
+/// As for the errors of the function, a conversion error (like a missing argument)
+/// is returned as an error table instead of being raised.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `name` - Name of the function, for the error message.
+/// * `args` - Arguments given by the script.
+/// * `f` - The function to call with the converted arguments.
+fn call_with_args<'lua, A, R, F>(
+    lua: &'lua Lua,
+    name: &str,
+    args: MultiValue<'lua>,
+    f: F,
+) -> LuaResult<(Value<'lua>, Value<'lua>)>
+where
+    A: FromLuaMulti<'lua>,
+    R: IntoLua<'lua>,
+    F: FnOnce(A) -> LuaResult<R>,
+{
+    let result = A::from_lua_multi(args, lua)
+        .map_err(|e| LuaError::RuntimeError(format!("Invalid arguments for {}: {}", name, e)))
+        .and_then(f);
+
+    to_lua_multi(lua, result)
+}
+
+/// Returns the options table given to a function, or an empty table if omitted.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `options` - The options table, if any.
+fn options_or_empty<'lua>(lua: &'lua Lua, options: Option<Table<'lua>>) -> LuaResult<Table<'lua>> {
+    match options {
+        Some(o) => Ok(o),
+        None => lua.create_table(),
+    }
+}
+
+/// Builds the error table returned to Lua, with the `kind` and
+/// the `message` of the error. The table can be printed as a string.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `e` - The error to convert.
+pub fn error_table<'lua>(lua: &'lua Lua, e: &LuaError) -> LuaResult<Table<'lua>> {
+    let t = lua.create_table()?;
+    ErrorExtLua::from(e).set_all(&t);
+
+    let meta = lua.create_table()?;
+    meta.set(
+        "__tostring",
+        lua.create_function(|_, t: Table| t.get::<_, String>("message"))?,
+    )?;
+    t.set_metatable(Some(meta));
+
+    Ok(t)
 }
 
 lazy_static! {
@@ -30,6 +138,28 @@ lazy_static! {
         .expect("Should create a tokio runtime");
 }
 
+/// Functions of kipt exposed as globals, and grouped in the `kipt` module.
+const KIPT_FUNCS: [&str; 18] = [
+    "logger_init",
+    "get_logger",
+    "state_init",
+    "state_get",
+    "print_str_array",
+    "declare",
+    "declare_class",
+    "deploy",
+    "invoke",
+    "estimate_fee",
+    "call",
+    "watch_tx",
+    "tx_status",
+    "get_nonce",
+    "new_keypair",
+    "account_address",
+    "estimate_deploy_account_fee",
+    "deploy_account",
+];
+
 /// Executes a lua program with a dedicated Lua context.
 /// The starknet context is automatically injected
 /// before intepreting the input program.
@@ -37,16 +167,194 @@ lazy_static! {
 /// # Arguments
 ///
 /// * `program` - Lua program to be executed.
-pub fn execute(program: &str) -> LuaResult<()> {
+/// * `script` - Path of the Lua program.
+/// * `script_args` - Arguments of the Lua program.
+/// * `args` - Command line arguments, some of them being set as globals.
+/// * `profile` - The profile of the `kipt.toml` file, if any.
+pub fn execute(
+    program: &str,
+    script: &Path,
+    script_args: &[String],
+    args: &Args,
+    profile: Option<&Profile>,
+) -> LuaResult<()> {
+    let lua = setup(args, profile)?;
+    setup_script_args(&lua, script, script_args)?;
+
+    let script_dir = match script.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    setup_package_path(&lua, script_dir, args, profile)?;
+
+    lua.load(program).exec()?;
+
+    logger::close(&lua)?;
+
+    Ok(())
+}
+
+/// Creates a Lua context with the starknet functions, and the globals
+/// set from the profile and the command line arguments.
+///
+/// # Arguments
+///
+/// * `args` - Command line arguments, some of them being set as globals.
+/// * `profile` - The profile of the `kipt.toml` file, if any.
+pub fn setup(args: &Args, profile: Option<&Profile>) -> LuaResult<Lua> {
     let lua = Lua::new();
 
+    if let Some(profile) = profile {
+        profile.set_globals(&lua)?;
+    }
+
+    let globals = lua.globals();
+
+    globals.set("DRY_RUN", args.dry_run)?;
+
+    if let Some(rpc) = &args.rpc {
+        globals.set("RPC", rpc.as_str())?;
+    }
+
+    if let Some(c) = &args.chain_id {
+        globals.set("CHAIN_ID", c.as_str())?;
+    }
+
+    if let Some(a) = &args.account_address {
+        globals.set("ACCOUNT_ADDRESS", a.as_str())?;
+    }
+
+    if let Some(f) = &args.account_file {
+        globals.set("ACCOUNT_FILE", f.to_string_lossy().to_string())?;
+    }
+
+    if let Some(k) = &args.private_key {
+        globals.set("ACCOUNT_PRIVKEY", k.as_str())?;
+    }
+
+    if let Some(k) = &args.keystore {
+        globals.set("ACCOUNT_KEYSTORE", k.to_string_lossy().to_string())?;
+    }
+
+    if let Some(f) = &args.export {
+        globals.set("EXPORT_TX", f.to_string_lossy().to_string())?;
+    }
+
+    let defines = lua.create_table()?;
+    for d in &args.define {
+        let (k, v) = d
+            .split_once('=')
+            .ok_or_else(|| Error::Other(format!("Invalid define {}, expected KEY=VALUE", d)))?;
+        defines.set(k, v)?;
+    }
+    globals.set("ARGS", defines)?;
+
     logger::setup(&lua)?;
 
+    state::setup(&lua)?;
+
     setup_starknet_funcs(&lua)?;
 
-    lua.load(program).exec()?;
+    setup_kipt_module(&lua)?;
 
-    logger::close(&lua)?;
+    drop(globals);
+
+    Ok(lua)
+}
+
+/// Prepends to `package.path` the directory of the Lua program and the lib
+/// directory, to `require` the modules of a project. The lib directory is the
+/// `--lib-path` argument, else the `lib_path` of the profile, else the `lib`
+/// directory next to the Lua program.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `script_dir` - Directory of the Lua program.
+/// * `args` - Command line arguments.
+/// * `profile` - The profile of the `kipt.toml` file, if any.
+pub fn setup_package_path(
+    lua: &Lua,
+    script_dir: &Path,
+    args: &Args,
+    profile: Option<&Profile>,
+) -> LuaResult<()> {
+    let lib_dir = match &args.lib_path {
+        Some(l) => l.clone(),
+        None => match profile.and_then(|p| p.lib_path.as_ref()) {
+            Some(l) => PathBuf::from(l),
+            None => script_dir.join("lib"),
+        },
+    };
+
+    let package: Table = lua.globals().get("package")?;
+    let path: String = package.get("path")?;
+
+    let mut dirs = vec![];
+    for dir in [script_dir, lib_dir.as_path()] {
+        let dir = dir.to_string_lossy();
+        dirs.push(format!("{}/?.lua;{}/?/init.lua", dir, dir));
+    }
+
+    package.set("path", format!("{};{}", dirs.join(";"), path))?;
+
+    Ok(())
+}
+
+/// Registers the built-in `kipt` module, grouping all the functions
+/// of kipt to be used with `require("kipt")`.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+fn setup_kipt_module(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+    for name in KIPT_FUNCS {
+        let f: Function = lua.globals().get(name)?;
+        module.set(name, f)?;
+    }
+
+    // Kept in the registry, the functions still being available
+    // even if the globals are overwritten by the program.
+    lua.set_named_registry_value("kipt_module", module)?;
+
+    let package: Table = lua.globals().get("package")?;
+    let preload: Table = package.get("preload")?;
+    preload.set(
+        "kipt",
+        lua.create_function(|lua, _: MultiValue| lua.named_registry_value::<Table>("kipt_module"))?,
+    )?;
+
+    Ok(())
+}
+
+/// Exposes the arguments of the Lua program: the `arg` table has the
+/// script path at index 0 followed by the raw arguments (as the standalone
+/// Lua interpreter does), and the named arguments are added to the `ARGS` table.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `script` - Path of the Lua program.
+/// * `script_args` - Arguments of the Lua program.
+fn setup_script_args(lua: &Lua, script: &Path, script_args: &[String]) -> LuaResult<()> {
+    let arg = lua.create_sequence_from(script_args.iter().cloned())?;
+    arg.set(0, script.to_string_lossy().to_string())?;
+    lua.globals().set("arg", arg)?;
+
+    let parsed: Table = lua.globals().get("ARGS")?;
+    let (named, positional) = args::parse_script_args(script_args);
+
+    for (k, v) in named {
+        match v {
+            Some(v) => parsed.set(k, v)?,
+            None => parsed.set(k, true)?,
+        }
+    }
+
+    for (idx, v) in (1..).zip(positional) {
+        parsed.set(idx, v)?;
+    }
 
     Ok(())
 }
@@ -81,7 +389,7 @@ fn setup_starknet_funcs(lua: &Lua) -> LuaResult<()> {
             out.push(']');
 
             let print: Function = lua.globals().get("print")?;
-            print.call::<_, _>(out)?;
+            print.call::<_, ()>(out)?;
 
             Ok(())
         })?,
@@ -89,52 +397,200 @@ fn setup_starknet_funcs(lua: &Lua) -> LuaResult<()> {
 
     lua.globals().set(
         "declare",
-        lua.create_function(|lua, (contract_name, options): (String, Table)| {
-            Ok(declare::lua_declare(lua, contract_name, options))
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "declare",
+                args,
+                |(contract_name, options): (String, Option<Table>)| {
+                    declare::lua_declare(lua, contract_name, options_or_empty(lua, options)?)
+                },
+            )
+        })?,
+    )?;
+
+    lua.globals().set(
+        "declare_class",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "declare_class",
+                args,
+                |(sierra_json, casm_json, options): (String, Option<String>, Option<Table>)| {
+                    declare::lua_declare_class(
+                        lua,
+                        sierra_json,
+                        casm_json,
+                        options_or_empty(lua, options)?,
+                    )
+                },
+            )
         })?,
     )?;
 
     lua.globals().set(
         "deploy",
-        lua.create_function(
-            |lua, (sierra_class_hash, args, options): (String, Vec<String>, Table)| {
-                Ok(deploy::lua_deploy(lua, sierra_class_hash, args, options))
-            },
-        )?,
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "deploy",
+                args,
+                |(sierra_class_hash, args, options): (String, Table, Option<Table>)| {
+                    deploy::lua_deploy(
+                        lua,
+                        sierra_class_hash,
+                        args,
+                        options_or_empty(lua, options)?,
+                    )
+                },
+            )
+        })?,
     )?;
 
     lua.globals().set(
         "invoke",
-        lua.create_function(|lua, (calls, options): (Vec<InvokeCall>, Table)| {
-            Ok(invoke::lua_invoke(lua, calls, options))
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "invoke",
+                args,
+                |(calls, options): (Vec<Table>, Option<Table>)| {
+                    invoke::lua_invoke(lua, calls, options_or_empty(lua, options)?)
+                },
+            )
+        })?,
+    )?;
+
+    lua.globals().set(
+        "estimate_fee",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "estimate_fee",
+                args,
+                |(calls, options): (Vec<Table>, Option<Table>)| {
+                    fee::lua_estimate_fee(lua, calls, options_or_empty(lua, options)?)
+                },
+            )
+        })?,
+    )?;
+
+    lua.globals().set(
+        "new_keypair",
+        lua.create_function(|lua, ()| to_lua_multi(lua, deploy_account::lua_new_keypair(lua)))?,
+    )?;
+
+    lua.globals().set(
+        "account_address",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "account_address",
+                args,
+                |(kind, public_key, options): (String, String, Option<Table>)| {
+                    deploy_account::lua_account_address(
+                        lua,
+                        kind,
+                        public_key,
+                        options_or_empty(lua, options)?,
+                    )
+                },
+            )
+        })?,
+    )?;
+
+    lua.globals().set(
+        "estimate_deploy_account_fee",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "estimate_deploy_account_fee",
+                args,
+                |(kind, private_key, options): (String, String, Option<Table>)| {
+                    deploy_account::lua_estimate_deploy_account_fee(
+                        lua,
+                        kind,
+                        private_key,
+                        options_or_empty(lua, options)?,
+                    )
+                },
+            )
+        })?,
+    )?;
+
+    lua.globals().set(
+        "deploy_account",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "deploy_account",
+                args,
+                |(kind, private_key, options): (String, String, Option<Table>)| {
+                    deploy_account::lua_deploy_account(
+                        lua,
+                        kind,
+                        private_key,
+                        options_or_empty(lua, options)?,
+                    )
+                },
+            )
+        })?,
+    )?;
+
+    lua.globals().set(
+        "tx_status",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(lua, "tx_status", args, |transaction_hash: String| {
+                transaction::lua_tx_status(lua, transaction_hash)
+            })
+        })?,
+    )?;
+
+    lua.globals().set(
+        "get_nonce",
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(lua, "get_nonce", args, |address: String| {
+                nonce::lua_get_nonce(lua, address)
+            })
         })?,
     )?;
 
     lua.globals().set(
         "call",
-        lua.create_function(
-            |lua,
-             (contract_address, function_name, calldata, options): (
-                String,
-                String,
-                Vec<String>,
-                Table,
-            )| {
-                Ok(call::lua_call(
-                    lua,
-                    contract_address,
-                    function_name,
-                    calldata,
-                    options,
-                ))
-            },
-        )?,
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "call",
+                args,
+                |(contract_address, function_name, calldata, options): (
+                    String,
+                    String,
+                    Table,
+                    Option<Table>,
+                )| {
+                    call::lua_call(
+                        lua,
+                        contract_address,
+                        function_name,
+                        calldata,
+                        options_or_empty(lua, options)?,
+                    )
+                },
+            )
+        })?,
     )?;
 
     lua.globals().set(
         "watch_tx",
-        lua.create_function(|lua, (transaction_hash, interval_ms): (String, Number)| {
-            Ok(transaction::lua_watch(lua, transaction_hash, interval_ms))
+        lua.create_function(|lua, args: MultiValue| {
+            call_with_args(
+                lua,
+                "watch_tx",
+                args,
+                |(transaction_hash, interval_ms): (String, Number)| {
+                    transaction::lua_watch(lua, transaction_hash, interval_ms)
+                },
+            )
         })?,
     )?;
 
@@ -142,37 +598,61 @@ fn setup_starknet_funcs(lua: &Lua) -> LuaResult<()> {
 }
 
 /// Retrieves account/provider/network information from Lua globals.
+/// The signer is built from `ACCOUNT_PRIVKEY` or `ACCOUNT_KEYSTORE`.
 ///
 /// # Arguments
 ///
 /// * `lua` - Lua VM instance.
-pub fn get_account(lua: &Lua) -> LuaResult<(String, String, String, bool)> {
+pub fn get_account(lua: &Lua) -> LuaResult<(String, String, KiptSigner, bool)> {
     let url_network: Option<String> = lua.globals().get("RPC")?;
-    let address: Option<String> = lua.globals().get("ACCOUNT_ADDRESS")?;
-    let privkey: Option<String> = lua.globals().get("ACCOUNT_PRIVKEY")?;
-    let is_legacy: Option<bool> = lua.globals().get("ACCOUNT_IS_LEGACY")?;
+    let address = get_account_address(lua)?;
 
-    match (url_network, address, privkey) {
-        (Some(un), Some(a), Some(p)) => {
-            if let Some(true) = is_legacy {
-                Ok((un, a, p, true))
-            } else {
-                Ok((un, a, p, false))
-            }
-        }
-        _ => {
-            // Without RPC and account info, we can't send tx. Panic here.
-            panic!(
-                r#"
-RPC, ACCOUNT_ADDRESS and ACCOUNT_PRIVKEY variables were required by a transaction, but one of them (or all) is not provided.
+    let signer = match (&url_network, &address) {
+        (Some(_), Some(_)) => signer::get_signer(lua)?,
+        _ => None,
+    };
+
+    match (url_network, address, signer) {
+        (Some(un), Some((a, is_legacy)), Some(s)) => Ok((un, a, s, is_legacy)),
+        // Without RPC and account info, we can't send tx.
+        _ => Err(Error::MissingGlobal(
+            r#"RPC, ACCOUNT_ADDRESS (or ACCOUNT_FILE) and ACCOUNT_PRIVKEY (or ACCOUNT_KEYSTORE) variables were required by a transaction, but one of them (or all) is not provided.
 Please consider setting RPC, ACCOUNT_ADDRESS and ACCOUNT_PRIVKEY variables at the top of you Lua script without the local keyword.
 
 RPC = "https://...."
 ACCOUNT_ADDRESS = "0x123..."
-ACCOUNT_PRIVKEY = "0x987..."
-"#,
-            );
+ACCOUNT_PRIVKEY = "0x987...""#
+                .to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Retrieves the account address and if it uses the legacy encoding, from
+/// the `ACCOUNT_ADDRESS` and `ACCOUNT_IS_LEGACY` globals. If `ACCOUNT_ADDRESS`
+/// is not set, the account file in the `ACCOUNT_FILE` global is used.
+/// Returns `None` if none of them is set.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn get_account_address(lua: &Lua) -> LuaResult<Option<(String, bool)>> {
+    let address: Option<String> = lua.globals().get("ACCOUNT_ADDRESS")?;
+    let account_file: Option<String> = lua.globals().get("ACCOUNT_FILE")?;
+    let is_legacy: Option<bool> = lua.globals().get("ACCOUNT_IS_LEGACY")?;
+
+    match (address, account_file) {
+        (Some(a), _) => Ok(Some((a, is_legacy.unwrap_or(false)))),
+        (None, Some(f)) => {
+            let descriptor = AccountDescriptor::load(Path::new(&f))?;
+            let address = descriptor.address()?;
+
+            Ok(Some((
+                format!("0x{:064x}", address),
+                is_legacy.unwrap_or(descriptor.is_legacy()),
+            )))
         }
+        (None, None) => Ok(None),
     }
 }
 
@@ -186,31 +666,224 @@ pub fn get_provider(lua: &Lua) -> LuaResult<String> {
 
     match url_network {
         Some(un) => Ok(un),
-        _ => {
-            // Without RPC, we can't make call. Panic here.
-            panic!(
-                r#"
-RPC variable was required by a call, but it's not provided.
+        // Without RPC, we can't make call.
+        None => Err(Error::MissingGlobal(
+            r#"RPC variable was required by a call, but it's not provided.
 Please consider setting RPC variable at the top of you Lua script
 without the local keyword.
 
-RPC = "https://...."
-"#,
-            );
+RPC = "https://....""#
+                .to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Retrieves the chain id from the `CHAIN_ID` global, if any.
+/// The chain id can be given in hexadecimal or as a short string (like "SN_GOERLI").
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn get_chain_id(lua: &Lua) -> LuaResult<Option<FieldElement>> {
+    let chain_id: Option<String> = lua.globals().get("CHAIN_ID")?;
+
+    match chain_id {
+        Some(c) if c.starts_with("0x") => {
+            Ok(Some(FieldElement::from_hex_be(&c).map_err(Error::from)?))
         }
+        Some(c) => Ok(Some(cairo_short_string_to_felt(&c).map_err(|e| {
+            Error::Other(format!("CHAIN_ID can't be parsed {}: {}", c, e))
+        })?)),
+        None => Ok(None),
+    }
+}
+
+/// Retrieves the dry-run mode from Lua globals.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn is_dry_run(lua: &Lua) -> LuaResult<bool> {
+    let dry_run: Option<bool> = lua.globals().get("DRY_RUN")?;
+    Ok(dry_run.unwrap_or(false))
+}
+
+/// Retrieves the path of the file where the signed transactions
+/// are exported instead of being sent, from the `EXPORT_TX` global.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn get_export_path(lua: &Lua) -> LuaResult<Option<PathBuf>> {
+    let path: Option<String> = lua.globals().get("EXPORT_TX")?;
+    Ok(path.map(PathBuf::from))
+}
+
+/// Retrieves the options shared by all the transactions.
+/// If no nonce is given, the tracked nonce of the current account is used, if any.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `table` - Lua table with the transaction options.
+pub fn get_tx_options(lua: &Lua, table: &Table) -> LuaResult<TxOptions> {
+    let mut tx_options = get_untracked_tx_options(lua, table)?;
+
+    if tx_options.nonce.is_none() {
+        tx_options.nonce = nonce::tracked(lua)?;
     }
+    tx_options.track_nonce = nonce::is_tracking(lua)?;
+
+    Ok(tx_options)
+}
+
+/// Retrieves the options shared by all the transactions, ignoring
+/// the nonce tracking of the current account.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `table` - Lua table with the transaction options.
+pub fn get_untracked_tx_options(lua: &Lua, table: &Table) -> LuaResult<TxOptions> {
+    let max_fee: Option<String> = table.get("max_fee")?;
+    let max_fee = match max_fee {
+        Some(f) => Some(FieldElement::from_str(&f).map_err(Error::from)?),
+        None => None,
+    };
+
+    let nonce: Option<String> = table.get("nonce")?;
+    let nonce = match nonce {
+        Some(n) => Some(FieldElement::from_str(&n).map_err(Error::from)?),
+        None => None,
+    };
+
+    Ok(TxOptions {
+        watch_interval: get_watch_from_options(lua, table)?,
+        dry_run: is_dry_run(lua)?,
+        max_fee,
+        fee_multiplier: table.get("fee_multiplier")?,
+        nonce,
+        track_nonce: false,
+        export: get_export_path(lua)?,
+    })
 }
 
-/// Retrieves the watch interval that may be present in the given lua table.
+/// Retrieves the watch interval that may be present in the given lua table,
+/// or the `WATCH_INTERVAL` global if the key is absent.
+/// An interval of 0 disables the watch.
 ///
 /// # Arguments
 ///
+/// * `lua` - Lua VM instance.
 /// * `table` - Lua table that may contain "watch_interval" key.
-pub fn get_watch_from_options(table: &Table) -> LuaResult<Option<Duration>> {
-    let o: Option<u32> = table.get("watch_interval")?;
-    if let Some(interval) = o {
-        Ok(Some(Duration::from_millis(interval.into())))
+pub fn get_watch_from_options(lua: &Lua, table: &Table) -> LuaResult<Option<Duration>> {
+    let o: Option<u32> = match table.get("watch_interval")? {
+        Some(interval) => Some(interval),
+        None => lua.globals().get("WATCH_INTERVAL")?,
+    };
+
+    match o {
+        Some(interval) if interval > 0 => Ok(Some(Duration::from_millis(interval.into()))),
+        _ => Ok(None),
+    }
+}
+
+/// Retrieves the artifacts path from the "artifacts_path" key of the given lua table,
+/// or the `ARTIFACTS_PATH` global if the key is absent.
+/// Defaults to the target directory of the Scarb project, else `./target/dev`.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `table` - Lua table that may contain "artifacts_path" key.
+pub fn get_artifacts_path(lua: &Lua, table: &Table) -> LuaResult<String> {
+    if let Some(path) = table.get::<_, Option<String>>("artifacts_path")? {
+        return Ok(path);
+    }
+
+    let path: Option<String> = lua.globals().get("ARTIFACTS_PATH")?;
+    Ok(
+        path.unwrap_or_else(|| match scarb::default_artifacts_dir() {
+            Some(d) => d.to_string_lossy().to_string(),
+            None => "./target/dev".to_string(),
+        }),
+    )
+}
+
+/// Retrieves the "compile_casm" key of the given lua table,
+/// or the `COMPILE_CASM` global if the key is absent. Defaults to false.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `table` - Lua table that may contain "compile_casm" key.
+pub fn get_compile_casm(lua: &Lua, table: &Table) -> LuaResult<bool> {
+    if let Some(b) = table.get::<_, Option<bool>>("compile_casm")? {
+        return Ok(b);
+    }
+
+    let b: Option<bool> = lua.globals().get("COMPILE_CASM")?;
+    Ok(b.unwrap_or(false))
+}
+
+/// Retrieves the "scarb_build" key of the given lua table,
+/// or the `SCARB_BUILD` global if the key is absent. Defaults to false.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `table` - Lua table that may contain "scarb_build" key.
+pub fn get_scarb_build(lua: &Lua, table: &Table) -> LuaResult<bool> {
+    if let Some(b) = table.get::<_, Option<bool>>("scarb_build")? {
+        return Ok(b);
+    }
+
+    let b: Option<bool> = lua.globals().get("SCARB_BUILD")?;
+    Ok(b.unwrap_or(false))
+}
+
+/// Retrieves the contract ABI if the "abi" key is present in the given lua table.
+/// The ABI is read from the Sierra artifact of the contract, using the same
+/// artifacts options as the declare.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `table` - Lua table that may contain "abi", "artifacts_path"
+///   and "artifacts_recursively" keys.
+pub fn get_abi_from_options(lua: &Lua, table: &Table) -> LuaResult<Option<ContractAbi>> {
+    let contract_name: Option<String> = table.get("abi")?;
+    let is_recursive: bool = table.get("artifacts_recursively")?;
+
+    if let Some(name) = contract_name {
+        Ok(Some(ContractAbi::from_artifacts(
+            &name,
+            &get_artifacts_path(lua, table)?,
+            is_recursive,
+        )?))
     } else {
         Ok(None)
     }
 }
+
+/// Converts Lua arguments into serialized felts (as hex strings).
+/// If an ABI is given, the arguments are encoded following the inputs types,
+/// else they are expected to be already serialized felts.
+///
+/// # Arguments
+///
+/// * `abi` - Optional contract ABI.
+/// * `inputs` - Function that returns the inputs to be encoded from the ABI.
+/// * `args` - Lua table containing the arguments.
+pub fn encode_args<F>(abi: Option<&ContractAbi>, inputs: F, args: &Table) -> LuaResult<Vec<String>>
+where
+    F: FnOnce(&ContractAbi) -> KiptResult<&[AbiNamedMember]>,
+{
+    if let Some(abi) = abi {
+        let felts: Vec<FieldElement> = abi.encode_inputs(inputs(abi)?, args)?;
+        Ok(felts.iter().map(|f| format!("0x{:064x}", f)).collect())
+    } else {
+        args.clone().sequence_values::<String>().collect()
+    }
+}
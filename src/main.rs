@@ -1,20 +1,39 @@
 //! Main file runing kipt.
 //!
 use anyhow::Result;
-use clap::Parser;
+use args::{Args, Command};
+use clap::{CommandFactory, Parser};
+use config::Profile;
+use mlua::Result as LuaResult;
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
 use tracing_subscriber::{fmt, layer::SubscriberExt, EnvFilter, Registry};
 
+mod abi;
 mod account;
 mod args;
+mod artifacts;
+mod broadcast;
 mod call;
+mod casm;
+mod commands;
+mod config;
 mod declare;
 mod deploy;
+mod deploy_account;
 mod error;
+mod export;
+mod fee;
 mod invoke;
 mod logger;
 mod lua;
+mod nonce;
+mod repl;
+mod scarb;
+mod signer;
+mod state;
 mod transaction;
 
 const VERSION_STRING: &str = env!("CARGO_PKG_VERSION");
@@ -23,20 +42,156 @@ const VERSION_STRING: &str = env!("CARGO_PKG_VERSION");
 fn main() -> Result<()> {
     init_tracing();
 
-    let args = args::Args::parse();
+    let args = Args::parse();
 
     if args.version {
         println!("{}", VERSION_STRING);
         return Ok(());
     }
 
-    if let Some(lua) = &args.lua {
-        let program = load_file(&lua.to_string_lossy())?;
-        Ok(lua::execute(&program)?)
-    } else {
-        // Help will be printed out by Args.
-        Ok(())
+    let profile = config::load_profile(args.profile.as_deref())?;
+    let profile = profile.as_ref();
+
+    let res = match &args.command {
+        Some(Command::Run { lua, script_args }) => run(lua, script_args, &args, profile)?,
+        Some(Command::Repl) => repl::start(&args, profile),
+        Some(Command::Call {
+            address,
+            function,
+            calldata,
+            block_id,
+            abi,
+        }) => lua::setup(&args, profile).and_then(|lua| {
+            commands::call(
+                &lua,
+                address,
+                function,
+                calldata,
+                block_id.as_deref(),
+                abi.as_deref(),
+            )
+        }),
+        Some(Command::Invoke {
+            address,
+            function,
+            calldata,
+            tx,
+        }) => lua::setup(&args, profile)
+            .and_then(|lua| commands::invoke(&lua, address, function, calldata, tx)),
+        Some(Command::Declare {
+            contract,
+            artifacts_path,
+            skip_if_declared,
+            scarb_build,
+            compile_casm,
+            tx,
+        }) => lua::setup(&args, profile).and_then(|lua| {
+            commands::declare(
+                &lua,
+                contract,
+                artifacts_path.as_deref(),
+                *skip_if_declared,
+                *scarb_build,
+                *compile_casm,
+                tx,
+            )
+        }),
+        Some(Command::Deploy {
+            class_hash,
+            args: ctor_args,
+            salt,
+            tx,
+        }) => lua::setup(&args, profile)
+            .and_then(|lua| commands::deploy(&lua, class_hash, ctor_args, salt.as_deref(), tx)),
+        Some(Command::TxStatus { tx_hash }) => {
+            lua::setup(&args, profile).and_then(|lua| commands::tx_status(&lua, tx_hash))
+        }
+        Some(Command::Broadcast {
+            file,
+            receipts,
+            watch_interval,
+        }) => {
+            broadcast(&args, profile, file, receipts.as_deref(), *watch_interval)?;
+            return Ok(());
+        }
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(*shell, &mut Args::command(), "kipt", &mut std::io::stdout());
+            return Ok(());
+        }
+        // Help will be printed out by Args if no program is given.
+        None => match &args.lua {
+            Some(lua) => run(lua, &[], &args, profile)?,
+            None => return Ok(()),
+        },
+    };
+
+    if let Err(e) = res {
+        eprintln!("error: {}", error::lua_error_message(&e));
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Runs a Lua program.
+///
+/// # Arguments
+///
+/// * `lua` - Path of the Lua program.
+/// * `script_args` - Arguments of the Lua program.
+/// * `args` - Command line arguments.
+/// * `profile` - The profile of the `kipt.toml` file, if any.
+fn run(
+    lua: &Path,
+    script_args: &[String],
+    args: &Args,
+    profile: Option<&Profile>,
+) -> Result<LuaResult<()>> {
+    let program = load_file(&lua.to_string_lossy())?;
+    Ok(lua::execute(&program, lua, script_args, args, profile))
+}
+
+/// Broadcasts the signed transactions of a file, exiting on error.
+///
+/// # Arguments
+///
+/// * `args` - Command line arguments.
+/// * `profile` - The profile of the `kipt.toml` file, if any.
+/// * `file` - Path of the file with the signed transactions.
+/// * `receipts` - Path of the file where the receipts are written.
+/// * `watch_interval` - Interval in milliseconds to poll the receipts.
+fn broadcast(
+    args: &Args,
+    profile: Option<&Profile>,
+    file: &Path,
+    receipts: Option<&Path>,
+    watch_interval: u64,
+) -> Result<()> {
+    let rpc = match &args.rpc {
+        Some(r) => Some(r.clone()),
+        None => profile.and_then(|p| p.rpc.clone()),
+    };
+
+    let Some(rpc) = rpc else {
+        eprintln!("error: an RPC is required to broadcast, use --rpc or a profile with `rpc`");
+        std::process::exit(1);
+    };
+
+    let receipts = receipts
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| file.with_extension("receipts.json"));
+
+    if let Err(e) = lua::RT.block_on(broadcast::broadcast(
+        file,
+        &rpc,
+        &receipts,
+        Duration::from_millis(watch_interval),
+    )) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     }
+
+    Ok(())
 }
 
 /// Loads a file content as `String`.
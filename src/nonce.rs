@@ -0,0 +1,286 @@
+//! Nonce management of the accounts.
+//! When the `NONCE_TRACKING` global is set, the nonce is tracked locally
+//! across consecutive transactions of the same account, which allows
+//! sending transactions back-to-back without waiting for them.
+use mlua::{Error as LuaError, Lua, Result as LuaResult};
+use starknet::{
+    accounts::ConnectedAccount,
+    core::types::{BlockId, BlockTag, FieldElement},
+    providers::Provider,
+};
+use std::collections::HashMap;
+
+use crate::account;
+use crate::error::{Error, KiptResult};
+use crate::lua::{self, RT};
+use crate::transaction::{TxOptions, TxOutcome};
+
+/// Next nonces to be used, by RPC and account address.
+#[derive(Debug, Default)]
+struct NonceTracker {
+    nonces: HashMap<(String, FieldElement), FieldElement>,
+}
+
+/// Defines a lua function that fetches the nonce of an account,
+/// on the pending block.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `address` - The account address.
+pub fn lua_get_nonce(lua: &Lua, address: String) -> LuaResult<u64> {
+    let url_network = lua::get_provider(lua)?;
+
+    let nonce = futures::executor::block_on(async move {
+        RT.spawn(async move {
+            let provider = account::setup_provider(&url_network).await?;
+            get_nonce(&provider, &address).await
+        })
+        .await
+        .unwrap()
+    })
+    .map_err(LuaError::from)?;
+
+    u64::try_from(nonce).map_err(|_| Error::Other(format!("Nonce too large: 0x{:x}", nonce)).into())
+}
+
+/// Fetches the nonce of an address on the pending block.
+///
+/// # Arguments
+///
+/// * `provider` - The provider to fetch the nonce from.
+/// * `address` - The account address.
+async fn get_nonce<P: Provider>(provider: &P, address: &str) -> KiptResult<FieldElement> {
+    Ok(provider
+        .get_nonce(
+            BlockId::Tag(BlockTag::Pending),
+            FieldElement::from_hex_be(address)?,
+        )
+        .await?)
+}
+
+/// Retrieves the nonce tracking mode from Lua globals.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn is_tracking(lua: &Lua) -> LuaResult<bool> {
+    let tracking: Option<bool> = lua.globals().get("NONCE_TRACKING")?;
+    Ok(tracking.unwrap_or(false))
+}
+
+/// Returns the tracked nonce of the current account, if any.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn tracked(lua: &Lua) -> LuaResult<Option<FieldElement>> {
+    if !is_tracking(lua)? {
+        return Ok(None);
+    }
+
+    let key = tracker_key(lua)?;
+
+    Ok(lua
+        .app_data_ref::<NonceTracker>()
+        .and_then(|t| t.nonces.get(&key).copied()))
+}
+
+/// Updates the tracked nonce of the current account after a transaction.
+/// If the transaction failed, the tracked nonce is discarded to be
+/// fetched again from the chain for the next transaction.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `outcome` - The outcome of the transaction, `None` if it failed.
+pub fn track(lua: &Lua, outcome: Option<&TxOutcome>) -> LuaResult<()> {
+    if !is_tracking(lua)? || lua::is_dry_run(lua)? {
+        return Ok(());
+    }
+
+    let key = tracker_key(lua)?;
+
+    if lua.app_data_ref::<NonceTracker>().is_none() {
+        lua.set_app_data(NonceTracker::default());
+    }
+
+    if let Some(mut tracker) = lua.app_data_mut::<NonceTracker>() {
+        match outcome.and_then(|o| o.nonce) {
+            Some(nonce) => tracker.nonces.insert(key, nonce + FieldElement::ONE),
+            None => tracker.nonces.remove(&key),
+        };
+    }
+
+    Ok(())
+}
+
+/// Resolves the nonce to be used for a transaction. When the nonce is
+/// neither given nor tracked, it's only fetched here if the tracking is
+/// enabled, else the account fetches it when the transaction is sent.
+///
+/// # Arguments
+///
+/// * `account` - The account sending the transaction.
+/// * `tx_options` - Options of the transaction.
+pub async fn resolve<A>(account: &A, tx_options: &TxOptions) -> KiptResult<Option<FieldElement>>
+where
+    A: ConnectedAccount + Sync,
+{
+    match tx_options.nonce {
+        Some(n) => Ok(Some(n)),
+        None if tx_options.track_nonce => Ok(Some(
+            account
+                .provider()
+                .get_nonce(BlockId::Tag(BlockTag::Pending), account.address())
+                .await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Returns the given nonce, or fetches the nonce of the account
+/// when the transaction hash must be computed before sending it.
+///
+/// # Arguments
+///
+/// * `account` - The account sending the transaction.
+/// * `nonce` - The resolved nonce, if any.
+pub async fn or_fetch<A>(account: &A, nonce: Option<FieldElement>) -> KiptResult<FieldElement>
+where
+    A: ConnectedAccount + Sync,
+{
+    match nonce {
+        Some(n) => Ok(n),
+        None => Ok(account.get_nonce().await?),
+    }
+}
+
+/// Builds the tracker key from the current `RPC` and account address.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+fn tracker_key(lua: &Lua) -> LuaResult<(String, FieldElement)> {
+    let url_network = lua::get_provider(lua)?;
+
+    // Parsed to share the key whatever the formatting of the address.
+    match lua::get_account_address(lua)? {
+        Some((a, _)) => Ok((
+            url_network,
+            FieldElement::from_hex_be(&a).map_err(Error::from)?,
+        )),
+        None => Err(Error::MissingGlobal(
+            "ACCOUNT_ADDRESS (or ACCOUNT_FILE) variable is required to track the nonce".to_string(),
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn felt(hex: &str) -> FieldElement {
+        FieldElement::from_hex_be(hex).unwrap()
+    }
+
+    fn outcome(nonce: &str) -> TxOutcome {
+        TxOutcome {
+            transaction_hash: felt("0x1"),
+            nonce: Some(felt(nonce)),
+            fee: None,
+            exported: false,
+        }
+    }
+
+    /// A Lua VM tracking the nonce of the account `address`.
+    fn lua_tracking(address: &str) -> Lua {
+        let lua = Lua::new();
+        lua.globals().set("RPC", "KATANA").unwrap();
+        lua.globals().set("ACCOUNT_ADDRESS", address).unwrap();
+        lua.globals().set("NONCE_TRACKING", true).unwrap();
+        lua
+    }
+
+    #[test]
+    fn track_then_tracked() {
+        let lua = lua_tracking("0x1234");
+
+        assert_eq!(tracked(&lua).unwrap(), None);
+
+        track(&lua, Some(&outcome("0x5"))).unwrap();
+        assert_eq!(tracked(&lua).unwrap(), Some(felt("0x6")));
+
+        track(&lua, Some(&outcome("0x6"))).unwrap();
+        assert_eq!(tracked(&lua).unwrap(), Some(felt("0x7")));
+    }
+
+    #[test]
+    fn failure_discards_the_tracked_nonce() {
+        let lua = lua_tracking("0x1234");
+
+        track(&lua, Some(&outcome("0x5"))).unwrap();
+        track(&lua, None).unwrap();
+
+        assert_eq!(tracked(&lua).unwrap(), None);
+    }
+
+    #[test]
+    fn not_tracked_when_disabled_or_dry_run() {
+        let lua = lua_tracking("0x1234");
+        lua.globals().set("DRY_RUN", true).unwrap();
+
+        track(&lua, Some(&outcome("0x5"))).unwrap();
+        assert_eq!(tracked(&lua).unwrap(), None);
+
+        lua.globals().set("DRY_RUN", false).unwrap();
+        track(&lua, Some(&outcome("0x5"))).unwrap();
+        lua.globals().set("NONCE_TRACKING", false).unwrap();
+        assert_eq!(tracked(&lua).unwrap(), None);
+    }
+
+    #[test]
+    fn nonces_are_tracked_by_rpc_and_account() {
+        let lua = lua_tracking("0x1234");
+        track(&lua, Some(&outcome("0x5"))).unwrap();
+
+        lua.globals().set("ACCOUNT_ADDRESS", "0x5678").unwrap();
+        assert_eq!(tracked(&lua).unwrap(), None);
+
+        lua.globals().set("ACCOUNT_ADDRESS", "0x1234").unwrap();
+        lua.globals().set("RPC", "GOERLI-1").unwrap();
+        assert_eq!(tracked(&lua).unwrap(), None);
+    }
+
+    #[test]
+    fn address_formatting_shares_the_key() {
+        let lua = lua_tracking("0x1");
+        track(&lua, Some(&outcome("0x5"))).unwrap();
+
+        lua.globals().set("ACCOUNT_ADDRESS", "0x01").unwrap();
+        assert_eq!(tracked(&lua).unwrap(), Some(felt("0x6")));
+
+        // The account file gives the same address, zero-padded.
+        let path = std::env::temp_dir().join(format!("kipt-nonce-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "version": 1,
+                "variant": { "type": "open_zeppelin", "version": 1, "public_key": "0x2" },
+                "deployment": { "status": "deployed", "class_hash": "0x3", "address": "0x1" }
+            }"#,
+        )
+        .unwrap();
+
+        lua.globals().set("ACCOUNT_ADDRESS", mlua::Nil).unwrap();
+        lua.globals()
+            .set("ACCOUNT_FILE", path.to_string_lossy().to_string())
+            .unwrap();
+        let nonce = tracked(&lua);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(nonce.unwrap(), Some(felt("0x6")));
+    }
+}
@@ -0,0 +1,167 @@
+//! An interactive Lua console, with the same context
+//! as the Lua programs executed by kipt.
+use mlua::{Error as LuaError, Lua, MultiValue, Result as LuaResult, Table, Value};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use std::path::{Path, PathBuf};
+
+use crate::args::Args;
+use crate::config::Profile;
+use crate::error::{self, Error};
+use crate::{logger, lua};
+
+/// Max depth of the nested tables printed, deeper tables are elided.
+const MAX_PRINT_DEPTH: usize = 6;
+
+/// Starts the REPL, until the end of the input (Ctrl-D).
+///
+/// # Arguments
+///
+/// * `args` - Command line arguments, some of them being set as globals.
+/// * `profile` - The profile of the `kipt.toml` file, if any.
+pub fn start(args: &Args, profile: Option<&Profile>) -> LuaResult<()> {
+    let lua = lua::setup(args, profile)?;
+    lua::setup_package_path(&lua, Path::new("."), args, profile)?;
+
+    let mut editor = DefaultEditor::new().map_err(|e| Error::Other(e.to_string()))?;
+    let history = history_path();
+    if let Some(h) = &history {
+        // No history yet on the first run.
+        let _ = editor.load_history(h);
+    }
+
+    println!("Kipt {} REPL, Ctrl-D to exit.", env!("CARGO_PKG_VERSION"));
+
+    let mut buffer = String::new();
+
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { ">> " };
+
+        let line = match editor.readline(prompt) {
+            Ok(l) => l,
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(Error::Other(e.to_string()).into()),
+        };
+
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(&line);
+
+        match eval(&lua, &buffer) {
+            Ok(values) => {
+                for v in values {
+                    println!("{}", pretty(&v, 0));
+                }
+            }
+            // Wait for the next lines of a multi-line statement.
+            Err(LuaError::SyntaxError {
+                incomplete_input: true,
+                ..
+            }) => continue,
+            Err(e) => eprintln!("error: {}", error::lua_error_message(&e)),
+        }
+
+        let _ = editor.add_history_entry(buffer.as_str());
+        buffer.clear();
+    }
+
+    if let Some(h) = &history {
+        if let Err(e) = editor.save_history(h) {
+            eprintln!("warning: history can't be saved: {}", e);
+        }
+    }
+
+    logger::close(&lua)?;
+
+    Ok(())
+}
+
+/// Evaluates the input, as an expression first to return its values
+/// (as the standalone Lua interpreter does), else as a statement.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `input` - The input to evaluate.
+fn eval<'lua>(lua: &'lua Lua, input: &str) -> LuaResult<MultiValue<'lua>> {
+    match lua
+        .load(format!("return {}", input))
+        .set_name("=repl")
+        .eval::<MultiValue>()
+    {
+        Err(LuaError::SyntaxError { .. }) => lua.load(input).set_name("=repl").eval(),
+        res => res,
+    }
+}
+
+/// Returns the path of the history file, if the home directory is known.
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".kipt_history"))
+}
+
+/// Formats a Lua value, with the nested tables indented.
+///
+/// # Arguments
+///
+/// * `value` - The value to format.
+/// * `depth` - The depth of the value in the printed tables.
+fn pretty(value: &Value, depth: usize) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s.to_string_lossy()),
+        Value::Table(t) => pretty_table(t, depth),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Nil => "nil".to_string(),
+        v => format!("<{}>", v.type_name()),
+    }
+}
+
+/// Formats a Lua table, the sequence first and then the other keys sorted.
+///
+/// # Arguments
+///
+/// * `table` - The table to format.
+/// * `depth` - The depth of the table in the printed tables.
+fn pretty_table(table: &Table, depth: usize) -> String {
+    if depth >= MAX_PRINT_DEPTH {
+        return "{ ... }".to_string();
+    }
+
+    let len = table.raw_len();
+    let mut sequence = vec![];
+    let mut entries = vec![];
+
+    for (k, v) in table.clone().pairs::<Value, Value>().flatten() {
+        match k {
+            Value::Integer(i) if i >= 1 && (i as usize) <= len => sequence.push((i, v)),
+            Value::String(s) => entries.push((s.to_string_lossy().to_string(), v)),
+            k => entries.push((format!("[{}]", pretty(&k, depth + 1)), v)),
+        }
+    }
+
+    if sequence.is_empty() && entries.is_empty() {
+        return "{}".to_string();
+    }
+
+    sequence.sort_by_key(|(i, _)| *i);
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let indent = "  ".repeat(depth + 1);
+    let mut out = String::from("{\n");
+
+    for (_, v) in sequence {
+        out.push_str(&format!("{}{},\n", indent, pretty(&v, depth + 1)));
+    }
+
+    for (k, v) in entries {
+        out.push_str(&format!("{}{} = {},\n", indent, k, pretty(&v, depth + 1)));
+    }
+
+    out.push_str(&format!("{}}}", "  ".repeat(depth)));
+    out
+}
@@ -0,0 +1,435 @@
+//! Integration with Scarb: the project is found from its `Scarb.toml`,
+//! and the contracts are resolved from the `*.starknet_artifacts.json`
+//! manifests emitted by `scarb build` in the target directory.
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+use tracing::trace;
+
+use crate::error::{Error, KiptResult};
+
+/// Name of the Scarb manifest.
+const SCARB_MANIFEST: &str = "Scarb.toml";
+
+/// Extension of the artifacts manifests emitted by Scarb.
+const ARTIFACTS_MANIFEST_EXT: &str = ".starknet_artifacts.json";
+
+/// Content of a `*.starknet_artifacts.json` file.
+#[derive(Debug, Deserialize)]
+struct ArtifactsManifest {
+    contracts: Vec<ContractEntry>,
+}
+
+/// A contract of an artifacts manifest.
+#[derive(Debug, Deserialize)]
+struct ContractEntry {
+    package_name: String,
+    contract_name: String,
+    /// Absent from the manifests of the old Scarb versions.
+    #[serde(default)]
+    module_path: Option<String>,
+    artifacts: ContractArtifactFiles,
+}
+
+/// Artifacts files of a contract, relative to the target directory.
+#[derive(Debug, Deserialize)]
+struct ContractArtifactFiles {
+    sierra: Option<String>,
+    casm: Option<String>,
+}
+
+impl ContractEntry {
+    /// Returns the Cairo module path of the contract.
+    fn module_path(&self) -> String {
+        self.module_path
+            .clone()
+            .unwrap_or(format!("{}::{}", self.package_name, self.contract_name))
+    }
+
+    /// Returns true if the contract matches the given name, which can be
+    /// the module path or a suffix of it (`MyContract`, `token::MyContract`),
+    /// the package-qualified name or the file stem of the artifacts.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the contract.
+    fn matches(&self, name: &str) -> bool {
+        let module_path = self.module_path();
+
+        module_path == name
+            || module_path.ends_with(&format!("::{}", name))
+            || name == format!("{}::{}", self.package_name, self.contract_name)
+            || name == format!("{}_{}", self.package_name, self.contract_name)
+    }
+}
+
+/// Returns the root of the Scarb project containing the given directory:
+/// the directory of the workspace `Scarb.toml` if any, else of the nearest `Scarb.toml`.
+///
+/// # Arguments
+///
+/// * `start` - The directory where the search starts.
+pub fn find_root(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    let mut root = None;
+
+    for dir in start.ancestors() {
+        let manifest = dir.join(SCARB_MANIFEST);
+        if !manifest.is_file() {
+            continue;
+        }
+
+        if root.is_none() {
+            root = Some(dir.to_path_buf());
+        }
+
+        if is_workspace(&manifest) {
+            return Some(dir.to_path_buf());
+        }
+    }
+
+    root
+}
+
+/// Returns true if the given `Scarb.toml` defines a workspace.
+///
+/// # Arguments
+///
+/// * `manifest` - Path of the `Scarb.toml` file.
+fn is_workspace(manifest: &Path) -> bool {
+    fs::read_to_string(manifest)
+        .ok()
+        .and_then(|c| c.parse::<toml::Table>().ok())
+        .is_some_and(|t| t.contains_key("workspace"))
+}
+
+/// Returns the directory of the artifacts of the Scarb project containing
+/// the current directory (`<root>/target/dev`, or as set by `SCARB_TARGET_DIR`
+/// and `SCARB_PROFILE`), if any.
+pub fn default_artifacts_dir() -> Option<PathBuf> {
+    let root = find_root(Path::new("."))?;
+
+    let target_dir = std::env::var_os("SCARB_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or(root.join("target"));
+    let profile = std::env::var("SCARB_PROFILE").unwrap_or("dev".to_string());
+
+    Some(target_dir.join(profile))
+}
+
+/// Returns the artifacts manifests of a directory.
+///
+/// # Arguments
+///
+/// * `artifacts_dir` - The directory where Scarb emits the artifacts.
+fn manifests(artifacts_dir: &Path) -> KiptResult<Vec<PathBuf>> {
+    let mut paths = vec![];
+
+    if !artifacts_dir.is_dir() {
+        return Ok(paths);
+    }
+
+    for entry in fs::read_dir(artifacts_dir)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_some_and(|f| f.to_string_lossy().ends_with(ARTIFACTS_MANIFEST_EXT))
+        {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// Locates the artifacts of a contract from the Scarb artifacts manifests.
+/// Returns `None` if there is no manifest, or if no contract matches the name.
+/// The Casm artifact is `None` if the manifest has no Casm artifact for the contract.
+///
+/// # Arguments
+///
+/// * `contract_name` - Name of the contract, which can be its module path.
+/// * `artifacts_dir` - The directory where Scarb emits the artifacts.
+pub fn locate_artifacts(
+    contract_name: &str,
+    artifacts_dir: &Path,
+) -> KiptResult<Option<(PathBuf, Option<PathBuf>)>> {
+    let mut candidates = vec![];
+
+    for path in manifests(artifacts_dir)? {
+        trace!("Reading artifacts manifest: {:?}", path);
+        let manifest: ArtifactsManifest = serde_json::from_str(&fs::read_to_string(&path)?)?;
+
+        candidates.extend(manifest.contracts);
+    }
+
+    select(contract_name, candidates, artifacts_dir)
+}
+
+/// Selects the contract matching the name among the contracts of the manifests,
+/// and returns the paths of its artifacts.
+///
+/// # Arguments
+///
+/// * `contract_name` - Name of the contract, which can be its module path.
+/// * `contracts` - The contracts of all the artifacts manifests.
+/// * `artifacts_dir` - The directory where Scarb emits the artifacts.
+fn select(
+    contract_name: &str,
+    contracts: Vec<ContractEntry>,
+    artifacts_dir: &Path,
+) -> KiptResult<Option<(PathBuf, Option<PathBuf>)>> {
+    let mut candidates = contracts
+        .into_iter()
+        .filter(|c| c.matches(contract_name))
+        .collect::<Vec<_>>();
+
+    if candidates.len() > 1 {
+        let list = candidates
+            .iter()
+            .map(|c| format!("  {}", c.module_path()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        return Err(Error::ArtifactsAmbiguous(format!(
+            "Several contracts match {}, use the module path of the contract:\n{}",
+            contract_name, list
+        )));
+    }
+
+    let Some(contract) = candidates.pop() else {
+        return Ok(None);
+    };
+
+    let Some(sierra) = contract.artifacts.sierra else {
+        return Err(Error::ArtifactsMissing(format!(
+            "Sierra artifacts not found for contract {}, `sierra = true` may be missing in {}",
+            contract_name, SCARB_MANIFEST
+        )));
+    };
+
+    Ok(Some((
+        artifacts_dir.join(sierra),
+        contract.artifacts.casm.map(|c| artifacts_dir.join(c)),
+    )))
+}
+
+/// Runs `scarb build` if the artifacts are older than the sources of the
+/// Scarb project (`Scarb.toml` and `.cairo` files), or if they are missing.
+/// Blocks until Scarb exits, it must not be called from an async task.
+///
+/// # Arguments
+///
+/// * `artifacts_dir` - The directory where Scarb emits the artifacts.
+pub fn build_if_stale(artifacts_dir: &Path) -> KiptResult<()> {
+    let root = find_root(Path::new("."))
+        .or_else(|| artifacts_dir.parent().and_then(find_root))
+        .ok_or(Error::Other(format!(
+            "{} not found, scarb build can't be run",
+            SCARB_MANIFEST
+        )))?;
+
+    let artifacts_time = manifests(artifacts_dir)?
+        .iter()
+        .filter_map(|p| modified(p))
+        .min();
+
+    let sources_time = latest_source(&root)?;
+
+    let is_stale = match (artifacts_time, sources_time) {
+        (Some(a), Some(s)) => s > a,
+        (None, _) => true,
+        (_, None) => false,
+    };
+
+    if !is_stale {
+        trace!("Artifacts are up to date in {:?}", artifacts_dir);
+        return Ok(());
+    }
+
+    trace!("Artifacts are stale, running scarb build in {:?}", root);
+
+    let output = Command::new("scarb")
+        .arg("--manifest-path")
+        .arg(root.join(SCARB_MANIFEST))
+        .arg("build")
+        .output()
+        .map_err(|e| Error::Other(format!("scarb can't be run: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::Other(format!(
+            "scarb build failed:\n{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns the last modification time of the sources of a Scarb project,
+/// the `target` directory and the hidden directories being ignored.
+///
+/// # Arguments
+///
+/// * `dir` - The directory of the sources.
+fn latest_source(dir: &Path) -> KiptResult<Option<SystemTime>> {
+    let mut latest = None;
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let time = if path.is_dir() {
+            if name == "target" || name.starts_with('.') {
+                continue;
+            }
+            latest_source(&path)?
+        } else if name == SCARB_MANIFEST || name.ends_with(".cairo") {
+            modified(&path)
+        } else {
+            continue;
+        };
+
+        latest = latest.max(time);
+    }
+
+    Ok(latest)
+}
+
+/// Returns the last modification time of a file, if available.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Contracts of a workspace with two packages, both having an `ERC20` contract.
+    fn contracts() -> Vec<ContractEntry> {
+        let manifest: ArtifactsManifest = serde_json::from_str(
+            r#"{
+                "version": 1,
+                "contracts": [
+                    {
+                        "id": "1",
+                        "package_name": "token",
+                        "contract_name": "ERC20",
+                        "module_path": "token::erc20::ERC20",
+                        "artifacts": {
+                            "sierra": "token_ERC20.contract_class.json",
+                            "casm": "token_ERC20.compiled_contract_class.json"
+                        }
+                    },
+                    {
+                        "id": "2",
+                        "package_name": "mocks",
+                        "contract_name": "ERC20",
+                        "module_path": "mocks::mock_erc20::ERC20",
+                        "artifacts": {
+                            "sierra": "mocks_ERC20.contract_class.json",
+                            "casm": null
+                        }
+                    },
+                    {
+                        "id": "3",
+                        "package_name": "token",
+                        "contract_name": "Vault",
+                        "artifacts": {
+                            "sierra": "token_Vault.contract_class.json",
+                            "casm": "token_Vault.compiled_contract_class.json"
+                        }
+                    },
+                    {
+                        "id": "4",
+                        "package_name": "token",
+                        "contract_name": "NoSierra",
+                        "module_path": "token::NoSierra",
+                        "artifacts": { "casm": "token_NoSierra.compiled_contract_class.json" }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        manifest.contracts
+    }
+
+    fn select_sierra(name: &str) -> KiptResult<Option<PathBuf>> {
+        Ok(select(name, contracts(), Path::new("target/dev"))?.map(|(sierra, _)| sierra))
+    }
+
+    #[test]
+    fn module_path_and_suffixes_match() {
+        let expected = Some(PathBuf::from("target/dev/token_ERC20.contract_class.json"));
+
+        assert_eq!(select_sierra("token::erc20::ERC20").unwrap(), expected);
+        assert_eq!(select_sierra("erc20::ERC20").unwrap(), expected);
+        assert_eq!(select_sierra("token::ERC20").unwrap(), expected);
+        assert_eq!(select_sierra("token_ERC20").unwrap(), expected);
+    }
+
+    #[test]
+    fn partial_names_dont_match() {
+        assert!(select_sierra("C20").unwrap().is_none());
+        assert!(select_sierra("oken::erc20::ERC20").unwrap().is_none());
+        assert!(select_sierra("Unknown").unwrap().is_none());
+    }
+
+    #[test]
+    fn same_name_in_several_packages_is_ambiguous() {
+        match select_sierra("ERC20") {
+            Err(Error::ArtifactsAmbiguous(m)) => {
+                assert!(m.contains("token::erc20::ERC20"));
+                assert!(m.contains("mocks::mock_erc20::ERC20"));
+            }
+            r => panic!("Expected an ambiguity error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn module_path_defaults_to_the_package() {
+        let (sierra, casm) = select("Vault", contracts(), Path::new("target/dev"))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            sierra,
+            PathBuf::from("target/dev/token_Vault.contract_class.json")
+        );
+        assert_eq!(
+            casm,
+            Some(PathBuf::from(
+                "target/dev/token_Vault.compiled_contract_class.json"
+            ))
+        );
+    }
+
+    #[test]
+    fn missing_artifacts() {
+        let (_, casm) = select(
+            "mocks::mock_erc20::ERC20",
+            contracts(),
+            Path::new("target/dev"),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(casm.is_none());
+
+        assert!(matches!(
+            select_sierra("NoSierra"),
+            Err(Error::ArtifactsMissing(_))
+        ));
+    }
+}
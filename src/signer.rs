@@ -0,0 +1,137 @@
+//! Signer of the transactions, built from the `ACCOUNT_PRIVKEY` global
+//! or from an encrypted JSON keystore (the format used by starkli)
+//! set in the `ACCOUNT_KEYSTORE` global.
+use async_trait::async_trait;
+use mlua::{Lua, Result as LuaResult};
+use starknet::{
+    core::{crypto::Signature, types::FieldElement},
+    signers::{local_wallet::SignError, Infallible, LocalWallet, Signer, SigningKey, VerifyingKey},
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+/// Signer of the account transactions.
+#[derive(Debug, Clone)]
+pub enum KiptSigner {
+    /// Private key from the `ACCOUNT_PRIVKEY` global.
+    PrivateKey(LocalWallet),
+    /// Key decrypted from the keystore of the `ACCOUNT_KEYSTORE` global.
+    Keystore(LocalWallet),
+}
+
+impl KiptSigner {
+    /// Returns the wallet holding the signing key.
+    fn wallet(&self) -> &LocalWallet {
+        match self {
+            Self::PrivateKey(w) | Self::Keystore(w) => w,
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for KiptSigner {
+    type GetPublicKeyError = Infallible;
+    type SignError = SignError;
+
+    async fn get_public_key(&self) -> Result<VerifyingKey, Self::GetPublicKeyError> {
+        self.wallet().get_public_key().await
+    }
+
+    async fn sign_hash(&self, hash: &FieldElement) -> Result<Signature, Self::SignError> {
+        self.wallet().sign_hash(hash).await
+    }
+}
+
+/// Keys already decrypted from keystores, by keystore path,
+/// to only ask for the password once per script.
+#[derive(Default)]
+struct KeystoreCache {
+    keys: HashMap<PathBuf, SigningKey>,
+}
+
+/// Retrieves the signer of the current account from Lua globals.
+/// Returns `None` if neither `ACCOUNT_PRIVKEY` nor `ACCOUNT_KEYSTORE` is set.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn get_signer(lua: &Lua) -> LuaResult<Option<KiptSigner>> {
+    let privkey: Option<String> = lua.globals().get("ACCOUNT_PRIVKEY")?;
+    let keystore: Option<String> = lua.globals().get("ACCOUNT_KEYSTORE")?;
+
+    match (privkey, keystore) {
+        (Some(_), Some(_)) => Err(Error::Other(
+            "ACCOUNT_PRIVKEY and ACCOUNT_KEYSTORE can't be used together, please set only one of them".to_string(),
+        )
+        .into()),
+        (Some(p), None) => Ok(Some(KiptSigner::PrivateKey(LocalWallet::from(
+            SigningKey::from_secret_scalar(FieldElement::from_hex_be(&p).map_err(Error::from)?),
+        )))),
+        (None, Some(k)) => Ok(Some(KiptSigner::Keystore(LocalWallet::from(
+            load_keystore(lua, PathBuf::from(k))?,
+        )))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Loads the signing key from a keystore, asking for the password
+/// only the first time the keystore is used.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `path` - Path of the keystore file.
+fn load_keystore(lua: &Lua, path: PathBuf) -> LuaResult<SigningKey> {
+    if let Some(key) = lua
+        .app_data_ref::<KeystoreCache>()
+        .and_then(|c| c.keys.get(&path).cloned())
+    {
+        return Ok(key);
+    }
+
+    if !path.exists() {
+        return Err(Error::Other(format!("Keystore file not found: {}", path.display())).into());
+    }
+
+    let password = keystore_password(lua, &path)?;
+    let key = SigningKey::from_keystore(&path, &password).map_err(Error::from)?;
+
+    if lua.app_data_ref::<KeystoreCache>().is_none() {
+        lua.set_app_data(KeystoreCache::default());
+    }
+
+    if let Some(mut cache) = lua.app_data_mut::<KeystoreCache>() {
+        cache.keys.insert(path, key.clone());
+    }
+
+    Ok(key)
+}
+
+/// Retrieves the password of a keystore, from the `ACCOUNT_KEYSTORE_PASSWORD`
+/// global, the file in the `ACCOUNT_KEYSTORE_PASSWORD_FILE` global,
+/// or prompts it if none is set.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `path` - Path of the keystore file.
+fn keystore_password(lua: &Lua, path: &Path) -> LuaResult<String> {
+    let password: Option<String> = lua.globals().get("ACCOUNT_KEYSTORE_PASSWORD")?;
+    if let Some(p) = password {
+        return Ok(p);
+    }
+
+    let password_file: Option<String> = lua.globals().get("ACCOUNT_KEYSTORE_PASSWORD_FILE")?;
+    if let Some(f) = password_file {
+        let content = fs::read_to_string(&f).map_err(Error::from)?;
+        return Ok(content.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    Ok(
+        rpassword::prompt_password(format!("Enter password for keystore {}: ", path.display()))
+            .map_err(Error::from)?,
+    )
+}
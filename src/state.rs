@@ -0,0 +1,347 @@
+//! A persistent deployment state, recording the results
+//! of the transactions by label and by network.
+//! If a labeled transaction is found in the state, it's not sent
+//! again, which makes the scripts resumable.
+use mlua::{Lua, Result as LuaResult, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::account;
+use crate::error::{Error, KiptResult};
+use crate::lua::{self, LuaTableSetable, RT};
+
+/// A transaction result recorded in the state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateEntry {
+    /// Kind of transaction (declare, deploy or invoke).
+    pub kind: String,
+    /// Data returned to Lua for this transaction.
+    #[serde(flatten)]
+    pub data: BTreeMap<String, String>,
+}
+
+impl LuaTableSetable for StateEntry {
+    fn set_all(&self, table: &Table) {
+        for (k, v) in &self.data {
+            table.set(k.as_str(), v.as_str()).unwrap();
+        }
+    }
+}
+
+/// The deployment state, with entries by label for each chain id.
+#[derive(Debug, Default)]
+pub struct DeploymentState {
+    path: PathBuf,
+    networks: BTreeMap<String, BTreeMap<String, StateEntry>>,
+}
+
+impl DeploymentState {
+    /// Loads the state from the given file, or initializes
+    /// an empty state if the file doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the state file.
+    pub fn load(path: &Path) -> KiptResult<Self> {
+        let networks = if path.exists() {
+            serde_json::from_reader(fs::File::open(path)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            networks,
+        })
+    }
+
+    /// Saves the state into its file.
+    pub fn save(&self) -> KiptResult<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.networks)?)?;
+        Ok(())
+    }
+
+    /// Returns the entry recorded for the label, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain_id` - The chain id of the network.
+    /// * `label` - The label of the transaction.
+    pub fn get(&self, chain_id: &str, label: &str) -> Option<&StateEntry> {
+        self.networks.get(chain_id).and_then(|n| n.get(label))
+    }
+
+    /// Records an entry for the label and saves the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain_id` - The chain id of the network.
+    /// * `label` - The label of the transaction.
+    /// * `entry` - The entry to record.
+    pub fn insert(&mut self, chain_id: &str, label: &str, entry: StateEntry) -> KiptResult<()> {
+        self.networks
+            .entry(chain_id.to_string())
+            .or_default()
+            .insert(label.to_string(), entry);
+
+        self.save()
+    }
+}
+
+/// Setups the state functions into the lua globals.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+pub fn setup(lua: &Lua) -> LuaResult<()> {
+    lua.globals().set(
+        "state_init",
+        lua.create_function(|lua, file_name: Option<String>| {
+            if lua.app_data_ref::<DeploymentState>().is_some() {
+                // Already initialized, do nothing.
+                return Ok(());
+            }
+
+            let path = PathBuf::from(file_name.unwrap_or("kipt.state.json".to_string()));
+            lua.set_app_data(DeploymentState::load(&path)?);
+
+            Ok(())
+        })?,
+    )?;
+
+    lua.globals().set(
+        "state_get",
+        lua.create_function(|lua, label: String| lookup(lua, &Some(label)))?,
+    )?;
+
+    Ok(())
+}
+
+/// Looks up the result of a labeled transaction on the current network.
+/// Returns `None` if the state is not initialized, no label is given
+/// or the label is not found.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `label` - The label of the transaction.
+pub fn lookup<'lua>(lua: &'lua Lua, label: &Option<String>) -> LuaResult<Option<Table<'lua>>> {
+    let label = match label {
+        Some(l) if lua.app_data_ref::<DeploymentState>().is_some() => l,
+        _ => return Ok(None),
+    };
+
+    let chain_id = chain_id(lua)?;
+
+    let entry = lua
+        .app_data_ref::<DeploymentState>()
+        .and_then(|s| s.get(&chain_id, label).cloned());
+
+    if let Some(e) = entry {
+        let t = lua.create_table()?;
+        e.set_all(&t);
+        Ok(Some(t))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Records the result of a labeled transaction on the current network.
+/// Does nothing if the state is not initialized, no label is given,
+/// during a dry-run or if the transactions are exported.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `label` - The label of the transaction.
+/// * `kind` - The kind of transaction.
+/// * `table` - The table returned to Lua for the transaction.
+pub fn record(lua: &Lua, label: &Option<String>, kind: &str, table: &Table) -> LuaResult<()> {
+    let label = match label {
+        Some(l)
+            if lua.app_data_ref::<DeploymentState>().is_some()
+                && !lua::is_dry_run(lua)?
+                && lua::get_export_path(lua)?.is_none() =>
+        {
+            l
+        }
+        _ => return Ok(()),
+    };
+
+    let chain_id = chain_id(lua)?;
+
+    let mut data = BTreeMap::new();
+    for pair in table.clone().pairs::<String, String>() {
+        let (k, v) = pair?;
+        data.insert(k, v);
+    }
+
+    if let Some(mut state) = lua.app_data_mut::<DeploymentState>() {
+        state.insert(
+            &chain_id,
+            label,
+            StateEntry {
+                kind: kind.to_string(),
+                data,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the chain id of the network currently set in the `RPC` global,
+/// or the `CHAIN_ID` global if set.
+/// The chain id is only fetched once per RPC.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+fn chain_id(lua: &Lua) -> LuaResult<String> {
+    if let Some(c) = lua::get_chain_id(lua)? {
+        return Ok(format!("0x{:x}", c));
+    }
+
+    let url_network = lua::get_provider(lua)?;
+
+    let chain_id = futures::executor::block_on(async move {
+        RT.spawn(async move {
+            let provider = account::setup_provider(&url_network).await?;
+            let chain_id = account::resolve_chain_id(&url_network, &provider, None).await?;
+            Ok::<_, Error>(format!("0x{:x}", chain_id))
+        })
+        .await
+        .unwrap()
+    })?;
+
+    Ok(chain_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a state file path unique to the test, removing any previous file.
+    fn state_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("kipt-state-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn entry(kind: &str, address: &str) -> StateEntry {
+        StateEntry {
+            kind: kind.to_string(),
+            data: BTreeMap::from([("deployed_address".to_string(), address.to_string())]),
+        }
+    }
+
+    /// A Lua VM with the state initialized from the given file, on the chain `CHAIN_ID`.
+    fn lua_with_state(path: &Path, chain_id: &str) -> Lua {
+        let lua = Lua::new();
+        lua.globals().set("CHAIN_ID", chain_id).unwrap();
+        lua.set_app_data(DeploymentState::load(path).unwrap());
+        lua
+    }
+
+    #[test]
+    fn load_missing_file_is_empty() {
+        let path = state_path("missing");
+
+        let state = DeploymentState::load(&path).unwrap();
+
+        assert!(state.get("0x1", "label").is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn insert_saves_and_reloads() {
+        let path = state_path("reload");
+
+        let mut state = DeploymentState::load(&path).unwrap();
+        state
+            .insert("0x1", "c1", entry("deploy", "0x1234"))
+            .unwrap();
+
+        let state = DeploymentState::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let e = state.get("0x1", "c1").unwrap();
+        assert_eq!(e.kind, "deploy");
+        assert_eq!(e.data["deployed_address"], "0x1234");
+    }
+
+    #[test]
+    fn entries_are_keyed_by_chain_id() {
+        let path = state_path("chains");
+
+        let mut state = DeploymentState::load(&path).unwrap();
+        state
+            .insert("0x1", "c1", entry("deploy", "0x1234"))
+            .unwrap();
+        state
+            .insert("0x2", "c1", entry("deploy", "0x5678"))
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            state.get("0x1", "c1").unwrap().data["deployed_address"],
+            "0x1234"
+        );
+        assert_eq!(
+            state.get("0x2", "c1").unwrap().data["deployed_address"],
+            "0x5678"
+        );
+        assert!(state.get("0x3", "c1").is_none());
+    }
+
+    #[test]
+    fn record_then_lookup() {
+        let path = state_path("lookup");
+        let lua = lua_with_state(&path, "0x1");
+        let label = Some("c1".to_string());
+
+        assert!(lookup(&lua, &label).unwrap().is_none());
+
+        let table = lua.create_table().unwrap();
+        table.set("deployed_address", "0x1234").unwrap();
+        record(&lua, &label, "deploy", &table).unwrap();
+
+        let t = lookup(&lua, &label).unwrap().unwrap();
+        assert_eq!(t.get::<_, String>("deployed_address").unwrap(), "0x1234");
+        assert!(lookup(&lua, &None).unwrap().is_none());
+
+        // Another chain doesn't see the entries of the first one.
+        lua.globals().set("CHAIN_ID", "0x2").unwrap();
+        assert!(lookup(&lua, &label).unwrap().is_none());
+
+        // The entry was saved, and is found again once the state is reloaded.
+        let lua = lua_with_state(&path, "0x1");
+        fs::remove_file(&path).unwrap();
+        assert!(lookup(&lua, &label).unwrap().is_some());
+    }
+
+    #[test]
+    fn record_is_skipped_in_dry_run() {
+        let path = state_path("dry_run");
+        let lua = lua_with_state(&path, "0x1");
+        lua.globals().set("DRY_RUN", true).unwrap();
+        let label = Some("c1".to_string());
+
+        let table = lua.create_table().unwrap();
+        table.set("deployed_address", "0x1234").unwrap();
+        record(&lua, &label, "deploy", &table).unwrap();
+
+        assert!(lookup(&lua, &label).unwrap().is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn lookup_without_state_is_none() {
+        let lua = Lua::new();
+
+        assert!(lookup(&lua, &Some("c1".to_string())).unwrap().is_none());
+    }
+}
@@ -1,15 +1,90 @@
-use anyhow::Result;
-use mlua::{Error as LuaError, Lua, Number, Result as LuaResult, Table};
+use mlua::{Lua, Number, Result as LuaResult, Table};
 use starknet::{
-    core::types::{ExecutionResult, FieldElement, StarknetError},
+    core::types::{ExecutionResult, FieldElement, MaybePendingTransactionReceipt, StarknetError},
     providers::{MaybeUnknownErrorCode, Provider, ProviderError, StarknetErrorWithMessage},
 };
+use std::path::PathBuf;
 use std::time::Duration;
 use tracing::trace;
 
 use crate::account;
-use crate::error::ErrorExtLua;
-use crate::lua::{self, LuaOutput, RT};
+use crate::error::{Error, KiptResult};
+use crate::fee::FeeOutput;
+use crate::lua::{self, LuaOutput, LuaTableSetable, RT};
+
+/// Options shared by all the transactions.
+#[derive(Debug, Clone, Default)]
+pub struct TxOptions {
+    /// Watch interval for the transaction receipt.
+    pub watch_interval: Option<Duration>,
+    /// If true, the transaction is not sent, only simulated or estimated.
+    pub dry_run: bool,
+    /// Max fee of the transaction. If `None`, the fee is estimated.
+    pub max_fee: Option<FieldElement>,
+    /// Multiplier applied to the estimated fee to compute the max fee.
+    pub fee_multiplier: Option<f64>,
+    /// Nonce of the transaction. If `None`, the nonce is fetched from the chain.
+    pub nonce: Option<FieldElement>,
+    /// If the nonce is tracked locally across transactions.
+    pub track_nonce: bool,
+    /// If set, the signed transaction is written to this file instead of being sent.
+    pub export: Option<PathBuf>,
+}
+
+/// Outcome of a transaction, sent or dry-run.
+#[derive(Debug, Clone)]
+pub struct TxOutcome {
+    /// Hash of the transaction (the hash it would have for a dry-run).
+    pub transaction_hash: FieldElement,
+    /// Nonce of the transaction, if resolved by kipt.
+    pub nonce: Option<FieldElement>,
+    /// Simulated or estimated fee, only for a dry-run.
+    pub fee: Option<FeeOutput>,
+    /// True if the transaction was exported instead of being sent.
+    pub exported: bool,
+}
+
+impl TxOutcome {
+    /// Appends the outcome to the output log.
+    ///
+    /// # Arguments
+    ///
+    /// * `out_log` - The output log of the transaction.
+    pub fn log(&self, out_log: &mut String) {
+        out_log.push_str(&format!(
+            "|     tx_hash      |  0x{:064x}  |\n",
+            self.transaction_hash
+        ));
+
+        if let Some(fee) = &self.fee {
+            out_log.push_str(&format!(
+                "|     dry-run      |  fee {} (gas {} @ {})  |\n",
+                fee.overall_fee, fee.gas_consumed, fee.gas_price
+            ));
+        }
+
+        if self.exported {
+            out_log.push_str("|     exported     |  yes  |\n");
+        }
+    }
+}
+
+impl LuaTableSetable for TxOutcome {
+    fn set_all(&self, table: &Table) {
+        table
+            .set("tx_hash", format!("0x{:064x}", self.transaction_hash))
+            .unwrap();
+
+        if let Some(fee) = &self.fee {
+            table.set("dry_run", true).unwrap();
+            fee.set_all(table);
+        }
+
+        if self.exported {
+            table.set("exported", true).unwrap();
+        }
+    }
+}
 
 /// Defines a lua function to watch a transaction from it's hash.
 /// Watching is polling the receipt until it's available on-chain.
@@ -26,13 +101,14 @@ pub fn lua_watch(
 ) -> LuaResult<Table<'_>> {
     let url_network = lua::get_provider(lua)?;
 
+    if lua::is_dry_run(lua)? {
+        // Transactions are not sent during a dry-run, nothing to watch.
+        return lua.create_table();
+    }
+
     let interval_ms = Duration::from_millis(interval_ms as u64);
-    let transaction_hash = FieldElement::from_hex_be(&transaction_hash).map_err(|_e| {
-        LuaError::ExternalError(std::sync::Arc::new(ErrorExtLua::new(&format!(
-            "Invalid FieldElement value: {}",
-            transaction_hash
-        ))))
-    })?;
+    let transaction_hash = FieldElement::from_hex_be(&transaction_hash)
+        .map_err(|_e| Error::Other(format!("Invalid FieldElement value: {}", transaction_hash)))?;
 
     let data = futures::executor::block_on(async move {
         RT.spawn(async move {
@@ -41,7 +117,7 @@ pub fn lua_watch(
                 Err(e) => {
                     return LuaOutput {
                         data: None,
-                        error: format!("{:?}", e),
+                        error: Some(e),
                     }
                 }
             };
@@ -49,11 +125,11 @@ pub fn lua_watch(
             match poll_exec_succeeded(provider, transaction_hash, interval_ms).await {
                 Ok(()) => LuaOutput {
                     data: Some(()),
-                    error: "".to_string(),
+                    error: None,
                 },
                 Err(e) => LuaOutput {
                     data: None,
-                    error: format!("{:?}", e),
+                    error: Some(e),
                 },
             }
         })
@@ -68,35 +144,188 @@ pub fn lua_watch(
 
         Ok(t)
     } else {
-        Err(LuaError::ExternalError(std::sync::Arc::new(
-            ErrorExtLua::new(&data.error),
-        )))
+        Err(lua::output_error(data.error))
     }
 }
 
+/// Status of a transaction.
+struct TxStatusOutput {
+    /// `NOT_RECEIVED`, `PENDING`, `ACCEPTED_ON_L2` or `ACCEPTED_ON_L1`.
+    pub finality_status: String,
+    /// `SUCCEEDED` or `REVERTED`, `None` if the transaction is not received.
+    pub execution_status: Option<String>,
+    pub revert_reason: Option<String>,
+    pub block_number: Option<u64>,
+}
+
+impl LuaTableSetable for TxStatusOutput {
+    fn set_all(&self, table: &Table) {
+        table
+            .set("finality_status", self.finality_status.clone())
+            .unwrap();
+        table
+            .set("execution_status", self.execution_status.clone())
+            .unwrap();
+        table
+            .set("revert_reason", self.revert_reason.clone())
+            .unwrap();
+        table.set("block_number", self.block_number).unwrap();
+    }
+}
+
+/// Defines a lua function that returns the status of a transaction,
+/// without waiting for the transaction to be executed.
+///
+/// # Arguments
+///
+/// * `lua` - Lua VM instance.
+/// * `transaction_hash` - The transaction hash to get the status of.
+pub fn lua_tx_status(lua: &Lua, transaction_hash: String) -> LuaResult<Table<'_>> {
+    let url_network = lua::get_provider(lua)?;
+
+    let transaction_hash = FieldElement::from_hex_be(&transaction_hash)
+        .map_err(|_e| Error::Other(format!("Invalid FieldElement value: {}", transaction_hash)))?;
+
+    let data = futures::executor::block_on(async move {
+        RT.spawn(async move {
+            let provider = match account::setup_provider(&url_network).await {
+                Ok(a) => a,
+                Err(e) => {
+                    return LuaOutput {
+                        data: None,
+                        error: Some(e),
+                    }
+                }
+            };
+
+            match tx_status(provider, transaction_hash).await {
+                Ok(status) => LuaOutput {
+                    data: Some(status),
+                    error: None,
+                },
+                Err(e) => LuaOutput {
+                    data: None,
+                    error: Some(e),
+                },
+            }
+        })
+        .await
+        .unwrap()
+    });
+
+    if let Some(d) = data.data {
+        let t = lua.create_table()?;
+        d.set_all(&t);
+
+        Ok(t)
+    } else {
+        Err(lua::output_error(data.error))
+    }
+}
+
+/// Fetches the status of a transaction from its receipt.
+///
+/// # Arguments
+///
+/// * `provider` - The provider to fetch the receipt from.
+/// * `transaction_hash` - The transaction hash to get the status of.
+async fn tx_status<P>(provider: P, transaction_hash: FieldElement) -> KiptResult<TxStatusOutput>
+where
+    P: Provider,
+{
+    let receipt = match provider.get_transaction_receipt(transaction_hash).await {
+        Ok(r) => r,
+        Err(ProviderError::StarknetError(StarknetErrorWithMessage {
+            code:
+                MaybeUnknownErrorCode::Known(
+                    StarknetError::TransactionHashNotFound | StarknetError::InvalidTransactionHash,
+                ),
+            ..
+        })) => {
+            return Ok(TxStatusOutput {
+                finality_status: "NOT_RECEIVED".to_string(),
+                execution_status: None,
+                revert_reason: None,
+                block_number: None,
+            })
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let (execution_status, revert_reason) = match receipt.execution_result() {
+        ExecutionResult::Succeeded => ("SUCCEEDED".to_string(), None),
+        ExecutionResult::Reverted { reason } => ("REVERTED".to_string(), Some(reason.clone())),
+    };
+
+    // The pending receipts have neither a finality status nor a block.
+    let json = serde_json::to_value(&receipt)?;
+    let finality_status = json
+        .get("finality_status")
+        .and_then(|s| s.as_str())
+        .unwrap_or("PENDING")
+        .to_string();
+    let block_number = json.get("block_number").and_then(|b| b.as_u64());
+
+    Ok(TxStatusOutput {
+        finality_status,
+        execution_status: Some(execution_status),
+        revert_reason,
+        block_number,
+    })
+}
+
+/// Polls the receipt of a transaction until the transaction is executed,
+/// returning an error if the execution reverted.
+///
+/// # Arguments
+///
+/// * `provider` - The provider to poll the receipt from.
+/// * `transaction_hash` - The transaction hash to poll receipt for.
+/// * `poll_interval` - Interval for the polling.
 pub async fn poll_exec_succeeded<P>(
     provider: P,
     transaction_hash: FieldElement,
     poll_interval: Duration,
-) -> Result<()>
+) -> KiptResult<()>
+where
+    P: Provider,
+{
+    let receipt = poll_receipt(&provider, transaction_hash, poll_interval).await?;
+
+    match receipt.execution_result() {
+        ExecutionResult::Succeeded => {
+            trace!(
+                "Transaction {} confirmed",
+                format!("0x{:064x}", transaction_hash)
+            );
+
+            Ok(())
+        }
+        ExecutionResult::Reverted { reason } => Err(Error::Reverted {
+            transaction_hash,
+            reason: reason.clone(),
+        }),
+    }
+}
+
+/// Polls the receipt of a transaction until it's available.
+///
+/// # Arguments
+///
+/// * `provider` - The provider to poll the receipt from.
+/// * `transaction_hash` - The transaction hash to poll receipt for.
+/// * `poll_interval` - Interval for the polling.
+pub async fn poll_receipt<P>(
+    provider: &P,
+    transaction_hash: FieldElement,
+    poll_interval: Duration,
+) -> KiptResult<MaybePendingTransactionReceipt>
 where
     P: Provider,
 {
     loop {
         match provider.get_transaction_receipt(transaction_hash).await {
-            Ok(receipt) => match receipt.execution_result() {
-                ExecutionResult::Succeeded => {
-                    trace!(
-                        "Transaction {} confirmed",
-                        format!("0x{:064x}", transaction_hash)
-                    );
-
-                    return Ok(());
-                }
-                ExecutionResult::Reverted { reason } => {
-                    return Err(anyhow::anyhow!("transaction reverted: {}", reason));
-                }
-            },
+            Ok(receipt) => return Ok(receipt),
             Err(ProviderError::StarknetError(StarknetErrorWithMessage {
                 code: MaybeUnknownErrorCode::Known(StarknetError::TransactionHashNotFound),
                 ..